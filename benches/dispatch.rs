@@ -0,0 +1,79 @@
+//! Compares the ways a script instance calls into mun: lifecycle callbacks through the
+//! table resolved when the assembly is loaded, other methods through the table's cache,
+//! and preparing the function on every call, as instances used to.
+//!
+//! Godot isn't needed, the functions take no arguments so no variants are converted.
+//! Run it with `cargo bench --bench dispatch`.
+
+use std::{
+    hint::black_box,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use godot_mun::{
+    dispatch::{FunctionTable, PreparedFunction},
+    lifecycle::Callback,
+};
+use mun_compiler::{Config, Driver, PathOrInline, RelativePathBuf};
+use mun_runtime::Runtime;
+
+/// calls per measurement, about what a frame with this many processing nodes does
+const CALLS: u32 = 10_000;
+const ROUNDS: u32 = 100;
+
+const SOURCE: &str = "
+pub fn ready() {}
+
+pub fn jump() {}
+";
+
+fn compile() -> PathBuf {
+    let out_dir = std::env::temp_dir().join("godot-mun-bench");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let config = Config {
+        out_dir: Some(out_dir),
+        ..Config::default()
+    };
+    let input = PathOrInline::Inline {
+        rel_path: RelativePathBuf::from("mod.mun"),
+        contents: SOURCE.to_owned(),
+    };
+    let (mut driver, file_id) = Driver::with_file(config, input).unwrap();
+    driver.write_all_assemblies(false).unwrap();
+    driver.assembly_output_path_from_file(file_id)
+}
+
+fn measure(name: &str, mut f: impl FnMut()) {
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..CALLS {
+            f();
+        }
+        best = best.min(start.elapsed());
+    }
+    println!(
+        "{name:>8}: {best:>10.2?} per {CALLS} calls, {:>6.1}ns per call",
+        best.as_nanos() as f64 / CALLS as f64
+    );
+}
+
+fn main() {
+    let assembly = compile();
+    let runtime = unsafe { Runtime::builder(&assembly).finish() }.unwrap();
+    let functions = FunctionTable::new(&runtime);
+
+    measure("by name", || {
+        let function = PreparedFunction::new(&runtime, black_box("jump")).unwrap();
+        black_box(unsafe { function.invoke(&runtime, None, &[]) }.unwrap());
+    });
+    measure("method", || {
+        let function = functions.method(&runtime, black_box("jump")).unwrap();
+        black_box(unsafe { function.invoke(&runtime, None, &[]) }.unwrap());
+    });
+    measure("callback", || {
+        let function = functions.callback(black_box(Callback::Ready)).unwrap();
+        black_box(unsafe { function.invoke(&runtime, None, &[]) }.unwrap());
+    });
+}
@@ -0,0 +1,484 @@
+//! Generates mun bindings for part of godot's class api, from the `extension_api.json`
+//! that `godot --dump-extension-api` writes.
+//!
+//! For every method that only takes and returns types mun can represent, this emits an
+//! `extern fn` plus a mun wrapper with the real types to `bindings_mun.rs`, and the rust
+//! shim implementing the extern to `bindings.rs`. Both end up in `OUT_DIR`, see
+//! `src/bindings.rs` and `src/compiler.rs`. The shims ptrcall the method binds of the
+//! api's hashes, so they have to be built against the godot the json was dumped from.
+//!
+//! `GODOT_EXTENSION_API` is the path of the json, `extension_api.json` next to Cargo.toml
+//! by default. `GODOT_MUN_CLASSES` is a comma separated list of the classes to generate
+//! bindings for. Scripts are only compiled with the bindings they use, so more classes
+//! mostly cost build time. Without the json no bindings are generated.
+//!
+//! It also passes the version of `mun_compiler` in Cargo.lock, or the one Cargo.toml asks
+//! for without a lock file, on as `MUN_COMPILER_VERSION`, which compiled assemblies are
+//! cached under.
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+const DEFAULT_CLASSES: &[&str] = &[
+    "Object",
+    "Node",
+    "CanvasItem",
+    "Node2D",
+    "Node3D",
+    "Sprite2D",
+    "AnimatedSprite2D",
+    "AnimationPlayer",
+    "CharacterBody2D",
+    "RigidBody2D",
+    "Area2D",
+    "Camera2D",
+    "Timer",
+    "AudioStreamPlayer",
+    "Label",
+];
+
+/// mun keywords, which can't be used as parameter names
+const KEYWORDS: &[&str] = &[
+    "break", "do", "else", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop",
+    "mod", "never", "package", "pub", "return", "self", "struct", "super", "true", "type", "use",
+    "while",
+];
+
+/// How a godot type is passed between mun and the shims.
+enum Kind {
+    Bool,
+    Int,
+    Float,
+    /// a godot math type from `godot.mun`, passed to the shim as its `f32` components and
+    /// returned as the value struct
+    Builtin(&'static str, &'static [&'static str]),
+    /// an `Object`, passed as its id. Only classes that aren't reference counted, the
+    /// `Ref`s godot passes those as would have to be released.
+    Object,
+    /// `String`, `StringName` or `NodePath`, only as an argument, passed as the id of a
+    /// name interned from a string literal
+    Name(&'static str),
+}
+
+impl Kind {
+    fn from_type(ty: &str, classes: &[String]) -> Option<Self> {
+        Some(match ty {
+            "bool" => Kind::Bool,
+            "int" => Kind::Int,
+            "float" => Kind::Float,
+            "Vector2" => Kind::Builtin("Vector2", &["x", "y"]),
+            "Vector3" => Kind::Builtin("Vector3", &["x", "y", "z"]),
+            "Color" => Kind::Builtin("Color", &["r", "g", "b", "a"]),
+            "Rect2" => Kind::Builtin("Rect2", &["position.x", "position.y", "size.x", "size.y"]),
+            "Quaternion" => Kind::Builtin("Quaternion", &["x", "y", "z", "w"]),
+            "Transform2D" => Kind::Builtin(
+                "Transform2D",
+                &["x.x", "x.y", "y.x", "y.y", "origin.x", "origin.y"],
+            ),
+            "String" => Kind::Name("string"),
+            "StringName" => Kind::Name("string_name"),
+            "NodePath" => Kind::Name("node_path"),
+            ty if ty.starts_with("enum::") || ty.starts_with("bitfield::") => Kind::Int,
+            ty if classes.iter().any(|class| class == ty) => Kind::Object,
+            _ => return None,
+        })
+    }
+
+    /// the mun type of a wrapper parameter or return value
+    fn mun_type(&self) -> &'static str {
+        match self {
+            Kind::Bool => "bool",
+            Kind::Int => "i64",
+            Kind::Float => "f64",
+            Kind::Builtin(name, _) => name,
+            Kind::Object => "Object",
+            Kind::Name(_) => "u64",
+        }
+    }
+
+    /// the types of the extern parameters a value is passed as
+    fn extern_types(&self) -> Vec<&'static str> {
+        match self {
+            Kind::Bool => vec!["bool"],
+            Kind::Int => vec!["i64"],
+            Kind::Float => vec!["f64"],
+            Kind::Builtin(_, components) => vec!["f32"; components.len()],
+            Kind::Object | Kind::Name(_) => vec!["u64"],
+        }
+    }
+
+    /// the mun expressions passing `name` to the extern
+    fn extern_args(&self, name: &str) -> Vec<String> {
+        match self {
+            Kind::Builtin(_, components) => components
+                .iter()
+                .map(|component| format!("{name}.{component}"))
+                .collect(),
+            Kind::Object => vec![format!("{name}.id")],
+            _ => vec![name.to_owned()],
+        }
+    }
+
+    /// the rust expression converting the shim parameters `args` to what godot expects
+    /// a pointer to
+    fn to_ptrcall(&self, args: &[String]) -> String {
+        match self {
+            Kind::Bool => format!("{} as u8", args[0]),
+            Kind::Int | Kind::Float => args[0].clone(),
+            Kind::Builtin(..) => format!("[{}]", args.join(", ")),
+            Kind::Object => format!("support::object({})", args[0]),
+            Kind::Name(name) => format!("support::{name}({})", args[0]),
+        }
+    }
+
+    /// the rust expression of the pointer to `name`, converted by `to_ptrcall`
+    fn ptrcall_arg(&self, name: &str) -> String {
+        match self {
+            Kind::Name(_) => format!("{name}.sys() as _"),
+            _ => format!("support::arg(&{name})"),
+        }
+    }
+}
+
+struct Method {
+    /// `<class>_<method>`, in snake case
+    name: String,
+    class: String,
+    godot_name: String,
+    /// identifies the method bind
+    hash: i64,
+    /// name and kind of the parameters
+    params: Vec<(String, Kind)>,
+    ret: Option<Kind>,
+}
+
+impl Method {
+    fn from_json(class: &str, method: &Value, classes: &[String]) -> Option<Self> {
+        let flag = |key: &str| method[key].as_bool().unwrap_or(false);
+        if flag("is_virtual") || flag("is_static") || flag("is_vararg") {
+            return None;
+        }
+        let godot_name = method["name"].as_str()?.to_owned();
+        let hash = method["hash"].as_i64()?;
+        let params = match method["arguments"].as_array() {
+            Some(arguments) => arguments
+                .iter()
+                .map(|argument| {
+                    let name = argument["name"].as_str()?;
+                    // `object` is the parameter taking the object the method is called on
+                    let name = if KEYWORDS.contains(&name) || name == "object" {
+                        format!("{name}_")
+                    } else {
+                        name.to_owned()
+                    };
+                    Some((name, Kind::from_type(argument["type"].as_str()?, classes)?))
+                })
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let ret = match method["return_value"]["type"].as_str() {
+            None => None,
+            Some(ty) => match Kind::from_type(ty, classes)? {
+                // there's no string type to return names in
+                Kind::Name(_) => return None,
+                kind => Some(kind),
+            },
+        };
+
+        Some(Self {
+            name: format!("{}_{godot_name}", snake_case(class)),
+            class: class.to_owned(),
+            godot_name,
+            hash,
+            params,
+            ret,
+        })
+    }
+
+    /// the extern and the wrapper calling it
+    fn mun(&self) -> String {
+        let mut out = String::new();
+        let extern_params = self
+            .params
+            .iter()
+            .flat_map(|(name, kind)| {
+                let types = kind.extern_types();
+                let single = types.len() == 1;
+                types.into_iter().enumerate().map(move |(i, ty)| {
+                    if single {
+                        format!("{name}: {ty}")
+                    } else {
+                        format!("{name}_{i}: {ty}")
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let extern_ret = match &self.ret {
+            None => String::new(),
+            Some(Kind::Builtin(name, _)) => format!(" -> {name}"),
+            Some(kind) => format!(" -> {}", kind.extern_types()[0]),
+        };
+        writeln!(
+            out,
+            "extern fn godot_{}(object: u64{}){extern_ret};",
+            self.name,
+            extern_params
+                .iter()
+                .map(|param| format!(", {param}"))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let params = self
+            .params
+            .iter()
+            .map(|(name, kind)| format!(", {name}: {}", kind.mun_type()))
+            .collect::<String>();
+        let ret = match &self.ret {
+            Some(kind) => format!(" -> {}", kind.mun_type()),
+            None => String::new(),
+        };
+        let args = self
+            .params
+            .iter()
+            .flat_map(|(name, kind)| kind.extern_args(name))
+            .map(|arg| format!(", {arg}"))
+            .collect::<String>();
+        let call = format!("godot_{}(object.id{args})", self.name);
+        let body = match &self.ret {
+            Some(Kind::Object) => format!("Object {{ id: {call} }}"),
+            _ => call,
+        };
+        writeln!(
+            out,
+            "fn {}(object: Object{params}){ret} {{\n    {body}\n}}",
+            self.name
+        )
+        .unwrap();
+        out
+    }
+
+    fn write_rust(&self, out: &mut String, register: &mut String) {
+        let mut shim_params = vec!["object: u64".to_owned()];
+        let mut converts = String::new();
+        let mut ptrcall_args = Vec::new();
+        let mut fn_types = vec!["u64"];
+        for (i, (_, kind)) in self.params.iter().enumerate() {
+            let types = kind.extern_types();
+            let names = (0..types.len())
+                .map(|j| format!("a{i}_{j}"))
+                .collect::<Vec<_>>();
+            for (name, ty) in names.iter().zip(&types) {
+                shim_params.push(format!("{name}: {ty}"));
+            }
+            fn_types.extend(types);
+            writeln!(converts, "    let a{i} = {};", kind.to_ptrcall(&names)).unwrap();
+            ptrcall_args.push(kind.ptrcall_arg(&format!("a{i}")));
+        }
+
+        let ret_type = match &self.ret {
+            None => String::new(),
+            Some(Kind::Builtin(name, _)) => format!(" -> values::{name}"),
+            Some(kind) => format!(" -> {}", kind.extern_types()[0]),
+        };
+        let call = |ret: &str| {
+            format!(
+                "unsafe {{ METHOD.call::<{ret}>(object, &[{}]) }}",
+                ptrcall_args.join(", ")
+            )
+        };
+        let body = match &self.ret {
+            None => format!("{};", call("()")),
+            Some(Kind::Bool) => format!("{}.is_some_and(|ret| ret != 0)", call("u8")),
+            Some(Kind::Int) => format!("{}.unwrap_or_default()", call("i64")),
+            Some(Kind::Float) => format!("{}.unwrap_or_default()", call("f64")),
+            Some(Kind::Builtin(name, _)) => {
+                format!("{}.unwrap_or_default()", call(&format!("values::{name}")))
+            }
+            Some(Kind::Object) => format!("{}.map_or(0, support::object_id)", call("usize")),
+            Some(Kind::Name(_)) => unreachable!("names can't be returned"),
+        };
+        writeln!(
+            out,
+            "extern \"C\" fn godot_{}({}){ret_type} {{\n    \
+             static METHOD: support::Method = support::Method::new({:?}, {:?}, {});\n\
+             {converts}    {body}\n}}\n",
+            self.name,
+            shim_params.join(", "),
+            self.class,
+            self.godot_name,
+            self.hash,
+        )
+        .unwrap();
+        writeln!(
+            register,
+            "        .insert_fn(\"godot_{0}\", godot_{0} as extern \"C\" fn({1}){ret_type})",
+            self.name,
+            fn_types.join(", "),
+        )
+        .unwrap();
+    }
+}
+
+/// `AnimationPlayer` to `animation_player`, `Node2D` to `node2d`
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(char::is_ascii_lowercase);
+            if prev.is_ascii_lowercase() || (prev.is_ascii_uppercase() && next_is_lower) {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Writes the mun definitions to `mun` as the entries of a `(name, definition)` array,
+/// so a script can be compiled with just the ones it names.
+fn generate(api: &Value, selected: &[String], mun: &mut String, rust: &mut String) {
+    let empty = Vec::new();
+    let all_classes = api["classes"].as_array().unwrap_or(&empty);
+    let class_names = all_classes
+        .iter()
+        .filter(|class| !class["is_refcounted"].as_bool().unwrap_or(false))
+        .filter_map(|class| Some(class["name"].as_str()?.to_owned()))
+        .collect::<Vec<_>>();
+
+    let mut register = String::new();
+    for class in all_classes {
+        let Some(name) = class["name"].as_str() else { continue };
+        if !selected.iter().any(|selected| selected == name) {
+            continue;
+        }
+
+        for en in class["enums"].as_array().unwrap_or(&empty) {
+            for value in en["values"].as_array().unwrap_or(&empty) {
+                let (Some(value_name), Some(value)) =
+                    (value["name"].as_str(), value["value"].as_i64())
+                else {
+                    continue;
+                };
+                let fn_name = format!("{}_{}", snake_case(name), value_name.to_ascii_lowercase());
+                let definition = format!("fn {fn_name}() -> i64 {{\n    {value}\n}}\n");
+                writeln!(mun, "    ({fn_name:?}, {definition:?}),").unwrap();
+            }
+        }
+        for method in class["methods"].as_array().unwrap_or(&empty) {
+            let Some(method) = Method::from_json(name, method, &class_names) else { continue };
+            writeln!(mun, "    ({:?}, {:?}),", method.name, method.mun()).unwrap();
+            method.write_rust(rust, &mut register);
+        }
+    }
+
+    writeln!(
+        rust,
+        "fn register_generated(builder: RuntimeBuilder) -> RuntimeBuilder {{\n    \
+         builder\n{register}}}"
+    )
+    .unwrap();
+}
+
+/// The string `key` is set to in `toml`, if it's on a line of its own.
+fn string_value<'a>(toml: &'a str, key: &str) -> Option<&'a str> {
+    toml.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix(key)?
+            .trim_start()
+            .strip_prefix('=')?;
+        value.trim().strip_prefix('"')?.strip_suffix('"')
+    })
+}
+
+/// The version of `mun_compiler` in Cargo.lock.
+fn locked_version(manifest_dir: &Path) -> Option<String> {
+    // in a workspace the lock file is next to the workspace's manifest
+    let lock_path = manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())?;
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    let lock = fs::read_to_string(&lock_path).ok()?;
+
+    lock.split("[[package]]")
+        .find_map(|package| {
+            (string_value(package, "name")? == "mun_compiler")
+                .then(|| string_value(package, "version"))?
+        })
+        .map(str::to_owned)
+}
+
+/// The version of `mun_compiler` that's actually built, from Cargo.lock.
+///
+/// There's no lock file when this is built as a dependency, then it's the version
+/// Cargo.toml asks for, which changes less often than the one that's built.
+fn mun_compiler_version(manifest_dir: &Path) -> String {
+    if let Some(version) = locked_version(manifest_dir) {
+        return version;
+    }
+    println!(
+        "cargo:warning=mun_compiler isn't in a Cargo.lock, compiled scripts are cached \
+         under the version Cargo.toml asks for"
+    );
+    fs::read_to_string(manifest_dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| string_value(&manifest, "mun_compiler").map(str::to_owned))
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GODOT_EXTENSION_API");
+    println!("cargo:rerun-if-env-changed=GODOT_MUN_CLASSES");
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    println!(
+        "cargo:rustc-env=MUN_COMPILER_VERSION={}",
+        mun_compiler_version(&manifest_dir)
+    );
+
+    let api_path = env::var_os("GODOT_EXTENSION_API")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("extension_api.json"));
+    println!("cargo:rerun-if-changed={}", api_path.display());
+    let classes: Vec<String> = match env::var("GODOT_MUN_CLASSES") {
+        Ok(classes) => classes
+            .split(',')
+            .map(|class| class.trim().to_owned())
+            .collect(),
+        Err(_) => DEFAULT_CLASSES
+            .iter()
+            .map(|&class| class.to_owned())
+            .collect(),
+    };
+
+    let mut mun = String::from("const BINDINGS: &[(&str, &str)] = &[\n");
+    let mut rust = String::new();
+    match fs::read_to_string(&api_path) {
+        Ok(json) => {
+            let api = serde_json::from_str(&json).expect("extension_api.json isn't valid json");
+            generate(&api, &classes, &mut mun, &mut rust);
+        }
+        Err(_) => {
+            println!(
+                "cargo:warning=no godot api at {}, mun scripts get no class bindings",
+                api_path.display()
+            );
+            generate(&Value::Null, &[], &mut mun, &mut rust);
+        }
+    }
+
+    mun.push_str("];\n");
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out_dir.join("bindings_mun.rs"), mun).unwrap();
+    fs::write(out_dir.join("bindings.rs"), rust).unwrap();
+}
@@ -0,0 +1,160 @@
+use mun_runtime::RuntimeBuilder;
+
+// used by the generated code, which is empty without an extension_api.json
+#[allow(unused_imports)]
+use godot::sys::GodotFfi;
+
+// the shims generated by build.rs, and `register_generated`
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Registers the externs of the generated bindings.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    register_generated(builder)
+}
+
+/// The math types of `godot.mun` as the shims return them. They're laid out like godot's
+/// own, so ptrcalls write them directly.
+pub mod values {
+    use mun_memory::{HasStaticType, StructMemoryKind, StructTypeBuilder, Type};
+    use once_cell::sync::OnceCell;
+
+    macro_rules! value_struct {
+        ($name:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+            #[repr(C)]
+            #[derive(Clone, Copy, Debug, Default)]
+            pub struct $name {
+                $(pub $field: $ty,)*
+            }
+
+            impl HasStaticType for $name {
+                fn type_info() -> &'static Type {
+                    static TYPE: OnceCell<Type> = OnceCell::new();
+                    // a script is the root module of its package, so the struct of the
+                    // prelude isn't qualified
+                    TYPE.get_or_init(|| {
+                        StructTypeBuilder::new(stringify!($name))
+                            .set_memory_kind(StructMemoryKind::Value)
+                            $(.add_field::<$ty>(stringify!($field)))*
+                            .finish()
+                    })
+                }
+            }
+        };
+    }
+
+    value_struct! { Vector2 { x: f32, y: f32 } }
+    value_struct! { Vector3 { x: f32, y: f32, z: f32 } }
+    value_struct! { Color { r: f32, g: f32, b: f32, a: f32 } }
+    value_struct! { Rect2 { position: Vector2, size: Vector2 } }
+    value_struct! { Quaternion { x: f32, y: f32, z: f32, w: f32 } }
+    value_struct! { Transform2D { x: Vector2, y: Vector2, origin: Vector2 } }
+}
+
+/// What the generated shims are written in terms of.
+mod support {
+    use godot::{
+        prelude::*,
+        sys::{self, interface_fn},
+    };
+    use once_cell::sync::OnceCell;
+
+    use crate::names;
+
+    /// A method of a godot class, whose bind is looked up on the first call.
+    pub struct Method {
+        class: &'static str,
+        name: &'static str,
+        hash: i64,
+        bind: OnceCell<usize>,
+    }
+
+    impl Method {
+        pub const fn new(class: &'static str, name: &'static str, hash: i64) -> Self {
+            Self {
+                class,
+                name,
+                hash,
+                bind: OnceCell::new(),
+            }
+        }
+
+        fn bind(&self) -> sys::GDExtensionMethodBindPtr {
+            *self.bind.get_or_init(|| {
+                let class = StringName::from(self.class);
+                let name = StringName::from(self.name);
+                unsafe {
+                    interface_fn!(classdb_get_method_bind)(
+                        class.string_sys() as _,
+                        name.string_sys() as _,
+                        self.hash,
+                    ) as usize
+                }
+            }) as sys::GDExtensionMethodBindPtr
+        }
+
+        /// Ptrcalls the method on the object with id `object`, `None` if it was freed.
+        ///
+        /// # Safety
+        ///
+        /// `args` have to point to the argument types of the method, and `R` has to be
+        /// laid out like its return type.
+        pub unsafe fn call<R: Default>(
+            &self,
+            object: u64,
+            args: &[sys::GDExtensionConstTypePtr],
+        ) -> Option<R> {
+            let bind = self.bind();
+            if bind.is_null() {
+                godot_error!(
+                    "{}.{} isn't in this version of godot",
+                    self.class,
+                    self.name
+                );
+                return None;
+            }
+            let object_ptr = interface_fn!(object_get_instance_from_id)(object);
+            if object_ptr.is_null() {
+                godot_error!("called {} on an object that doesn't exist", self.name);
+                return None;
+            }
+            let mut ret = R::default();
+            interface_fn!(object_method_bind_ptrcall)(
+                bind,
+                object_ptr,
+                args.as_ptr(),
+                &mut ret as *mut R as sys::GDExtensionTypePtr,
+            );
+            Some(ret)
+        }
+    }
+
+    pub fn arg<T>(value: &T) -> sys::GDExtensionConstTypePtr {
+        value as *const T as sys::GDExtensionConstTypePtr
+    }
+
+    /// The object with id `id`, null if there's none. Godot takes objects as a pointer to
+    /// this.
+    pub fn object(id: u64) -> sys::GDExtensionObjectPtr {
+        unsafe { interface_fn!(object_get_instance_from_id)(id) }
+    }
+
+    /// The id of an object a ptrcall returned, 0 for null.
+    pub fn object_id(object: usize) -> u64 {
+        if object == 0 {
+            return 0;
+        }
+        unsafe { interface_fn!(object_get_instance_id)(object as sys::GDExtensionObjectPtr) }
+    }
+
+    pub fn string(id: u64) -> GodotString {
+        names::lookup_string(id).unwrap_or_default()
+    }
+
+    pub fn string_name(id: u64) -> StringName {
+        names::lookup(id).unwrap_or_default()
+    }
+
+    pub fn node_path(id: u64) -> NodePath {
+        names::lookup_path(id).unwrap_or_default()
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use godot::{engine::ProjectSettings, prelude::*};
+use mun_compiler::Config;
+use once_cell::sync::OnceCell;
+
+/// where cached assemblies are stored
+const CACHE_DIR: &str = "res://.godot/mun_cache";
+
+/// entries that haven't been used for this long are removed by [`collect_garbage`]
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+/// Absolute path of the cache directory.
+///
+/// Resolved once, on the main thread, so the compile worker never has to touch
+/// `ProjectSettings`.
+pub fn cache_dir() -> PathBuf {
+    static DIR: OnceCell<PathBuf> = OnceCell::new();
+    DIR.get_or_init(|| {
+        let dir = ProjectSettings::singleton().globalize_path(GodotString::from(CACHE_DIR));
+        PathBuf::from(String::from(&dir))
+    })
+    .clone()
+}
+
+/// Hash of everything that influences the compiled assembly.
+///
+/// `DefaultHasher` isn't guaranteed to be stable across rust versions, that only costs
+/// a recompile though.
+pub fn key(source: &str, config: &Config) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    // assemblies of another compiler may not be compatible with the runtime
+    env!("MUN_COMPILER_VERSION").hash(&mut hasher);
+    config.target.llvm_target.hash(&mut hasher);
+    format!("{:?}", config.optimization_lvl).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Entries are named `<script hash>-<key>.munlib`, so every script has at most one
+/// entry and replacing it removes the stale one.
+fn script_prefix(script_path: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    script_path.hash(&mut hasher);
+    format!("{:016x}-", hasher.finish())
+}
+
+fn entry_path(script_path: &str, key: u64) -> PathBuf {
+    cache_dir().join(format!("{}{key:016x}.munlib", script_prefix(script_path)))
+}
+
+/// Returns the cached assembly of `script_path` compiled with `key`, if any.
+pub fn lookup(script_path: &str, key: u64) -> Option<PathBuf> {
+    let path = entry_path(script_path, key);
+    if !path.is_file() {
+        return None;
+    }
+    // mark the entry as used, so it survives garbage collection
+    if let Ok(file) = fs::File::options().append(true).open(&path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(path)
+}
+
+/// Moves the freshly compiled `assembly` into the cache, replacing any older entry of
+/// `script_path`.
+pub fn store(script_path: &str, key: u64, assembly: &Path) -> io::Result<PathBuf> {
+    let dir = cache_dir();
+    fs::create_dir_all(&dir)?;
+
+    let prefix = script_prefix(script_path);
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    let path = entry_path(script_path, key);
+    fs::rename(assembly, &path).or_else(|_| fs::copy(assembly, &path).map(|_| ()))?;
+    Ok(path)
+}
+
+/// Removes entries that haven't been used for a while, such as those of deleted scripts.
+pub fn collect_garbage() -> io::Result<()> {
+    let Ok(entries) = fs::read_dir(cache_dir()) else { return Ok(()) };
+    let now = SystemTime::now();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "munlib") {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() > MAX_UNUSED_AGE {
+            println!("removing stale cache entry {}", path.display());
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_depends_on_source() {
+        let config = Config::default();
+        assert_eq!(key("pub fn a() {}", &config), key("pub fn a() {}", &config));
+        assert_ne!(key("pub fn a() {}", &config), key("pub fn b() {}", &config));
+    }
+
+    #[test]
+    fn key_ignores_out_dir() {
+        // every script compiles to its own directory
+        let config = Config {
+            out_dir: Some(PathBuf::from("build/a")),
+            ..Config::default()
+        };
+        assert_eq!(key("", &config), key("", &Config::default()));
+    }
+
+    #[test]
+    fn entries_of_a_script_share_a_prefix() {
+        assert_eq!(
+            script_prefix("res://player.mun"),
+            script_prefix("res://player.mun")
+        );
+        assert_ne!(
+            script_prefix("res://player.mun"),
+            script_prefix("res://enemy.mun")
+        );
+        assert!(script_prefix("res://player.mun").ends_with('-'));
+    }
+}
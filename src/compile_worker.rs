@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use godot::prelude::*;
+use once_cell::sync::Lazy;
+
+use crate::{compile_cache, compiler};
+
+/// a request to compile the source of a script
+pub struct CompileJob {
+    /// the `MunScript` to report back to
+    pub script: InstanceId,
+    pub script_path: String,
+    pub source: String,
+}
+
+pub struct CompileResult {
+    pub script: InstanceId,
+    pub script_path: String,
+    pub result: Result<PathBuf, String>,
+}
+
+/// Compiles scripts on a background thread, so the editor stays responsive while llvm
+/// is busy. Results are collected by [`poll`], which runs every frame.
+struct CompileWorker {
+    jobs: Mutex<Sender<CompileJob>>,
+    results: Mutex<Receiver<CompileResult>>,
+}
+
+static WORKER: Lazy<CompileWorker> = Lazy::new(|| {
+    let (job_sender, job_receiver) = channel::<CompileJob>();
+    let (result_sender, result_receiver) = channel();
+    thread::Builder::new()
+        .name("mun compiler".into())
+        .spawn(move || run(job_receiver, result_sender))
+        .expect("failed to spawn the mun compile worker");
+
+    CompileWorker {
+        jobs: Mutex::new(job_sender),
+        results: Mutex::new(result_receiver),
+    }
+});
+
+fn run(jobs: Receiver<CompileJob>, results: Sender<CompileResult>) {
+    while let Ok(job) = jobs.recv() {
+        // while someone is typing several jobs for the same script pile up, only the
+        // newest one is worth compiling
+        let mut pending = HashMap::new();
+        for job in std::iter::once(job).chain(jobs.try_iter()) {
+            pending.insert(job.script_path.clone(), job);
+        }
+
+        for (_, job) in pending {
+            let result = compiler::compile(&job.script_path, &job.source);
+            let result = CompileResult {
+                script: job.script,
+                script_path: job.script_path,
+                result,
+            };
+            if results.send(result).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Queues `job` to be compiled in the background.
+pub fn submit(job: CompileJob) {
+    println!("queueing compilation of {}", job.script_path);
+    // make sure the cache directory is resolved here, on the main thread
+    compile_cache::cache_dir();
+    WORKER.jobs.lock().unwrap().send(job).unwrap();
+}
+
+/// Returns all compilations that finished since the last call, never blocks.
+pub fn poll() -> Vec<CompileResult> {
+    WORKER.results.lock().unwrap().try_iter().collect()
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use mun_compiler::{Config, DisplayColor, Driver, PathOrInline, RelativePathBuf};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{compile_cache, names};
+
+/// directory inside the cache that the compiler writes the assembly of `script_path` to,
+/// before it's moved into the cache proper
+fn build_dir(script_path: &str) -> PathBuf {
+    compile_cache::cache_dir()
+        .join("build")
+        .join(script_path.trim_start_matches("res://"))
+        .with_extension("")
+}
+
+/// Where the assembly of `script_path` is published for runtimes to load.
+///
+/// Unlike cache entries this path doesn't change between compilations, which is what
+/// lets runtimes notice new versions and hot reload them.
+pub fn live_path(script_path: &str) -> PathBuf {
+    compile_cache::cache_dir()
+        .join("live")
+        .join(script_path.trim_start_matches("res://"))
+        .with_extension("munlib")
+}
+
+/// Copies `assembly` to the [`live_path`] of `script_path`, unless it's already there.
+pub fn publish(script_path: &str, assembly: &Path) -> io::Result<PathBuf> {
+    let live_path = live_path(script_path);
+    let contents = fs::read(assembly)?;
+    if fs::read(&live_path).is_ok_and(|live| live == contents) {
+        return Ok(live_path);
+    }
+
+    if let Some(dir) = live_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // write next to it and rename, so a watching runtime never sees half an assembly
+    let staging_path = live_path.with_extension("munlib.tmp");
+    fs::write(&staging_path, contents)?;
+    fs::rename(&staging_path, &live_path)?;
+    Ok(live_path)
+}
+
+/// mun definitions every script is compiled with
+const PRELUDE: &str = include_str!("godot.mun");
+
+// `BINDINGS`, the name and mun definition of every binding to godot's classes that
+// build.rs generated
+include!(concat!(env!("OUT_DIR"), "/bindings_mun.rs"));
+
+/// The definitions of the bindings `source` names, so scripts aren't compiled with all
+/// of them.
+fn used_bindings(source: &str) -> String {
+    static IDENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[a-z_][a-z0-9_]*\b").unwrap());
+    let idents = IDENT
+        .find_iter(source)
+        .map(|ident| ident.as_str())
+        .collect::<HashSet<_>>();
+    BINDINGS
+        .iter()
+        .filter(|(name, _)| idents.contains(name))
+        .map(|(_, definition)| *definition)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rewrites the string literals of `source` and appends the prelude, after it so line
+/// numbers in diagnostics still match.
+fn with_prelude(source: &str) -> String {
+    format!(
+        "{}\n{PRELUDE}\n{}",
+        names::rewrite_literals(source),
+        used_bindings(source)
+    )
+}
+
+fn config(script_path: &str) -> Config {
+    Config {
+        out_dir: Some(build_dir(script_path)),
+        ..Config::default()
+    }
+}
+
+/// The cached assembly of `source`, if it was compiled before, without compiling it.
+pub fn cached(script_path: &str, source: &str) -> Option<PathBuf> {
+    let key = compile_cache::key(&with_prelude(source), &config(script_path));
+    compile_cache::lookup(script_path, key)
+}
+
+/// Compiles `source`, the contents of the script at `script_path`, into a `.munlib`.
+///
+/// Returns the absolute path of the assembly, or the rendered compiler diagnostics if
+/// compilation failed. Sources that were compiled before are served from the cache.
+pub fn compile(script_path: &str, source: &str) -> Result<PathBuf, String> {
+    let source = with_prelude(source);
+    let config = config(script_path);
+    let key = compile_cache::key(&source, &config);
+    if let Some(path) = compile_cache::lookup(script_path, key) {
+        println!("cache hit for {script_path}");
+        return Ok(path);
+    }
+
+    std::fs::create_dir_all(build_dir(script_path)).map_err(|err| err.to_string())?;
+    // every script is the root module of its own package, so the names of its types
+    // aren't qualified by a module path
+    let input = PathOrInline::Inline {
+        rel_path: RelativePathBuf::from("mod.mun"),
+        contents: source,
+    };
+    let (mut driver, file_id) = Driver::with_file(config, input).map_err(|err| err.to_string())?;
+
+    let mut diagnostics = Vec::new();
+    let has_errors = driver
+        .emit_diagnostics(&mut diagnostics, DisplayColor::Disable)
+        .map_err(|err| err.to_string())?;
+    if has_errors {
+        return Err(String::from_utf8_lossy(&diagnostics).into_owned());
+    }
+
+    driver
+        .write_all_assemblies(false)
+        .map_err(|err| err.to_string())?;
+    let built = driver.assembly_output_path_from_file(file_id);
+    compile_cache::store(script_path, key, &built).map_err(|err| err.to_string())
+}
@@ -0,0 +1,278 @@
+use godot::prelude::*;
+use mun_runtime::RuntimeBuilder;
+
+use crate::{
+    handles::{get, insert},
+    names,
+};
+
+// `String`, `Array` and `Dictionary` handles, they share the table of `handles.rs` with
+// `Variant` and are released the same way. Handles of these types always refer to a
+// value of the type or to nil, which reads as empty.
+
+fn string(handle: u64) -> GodotString {
+    get(handle).try_to().unwrap_or_default()
+}
+
+fn array(handle: u64) -> VariantArray {
+    get(handle).try_to().unwrap_or_default()
+}
+
+fn dictionary(handle: u64) -> Dictionary {
+    get(handle).try_to().unwrap_or_default()
+}
+
+/// A godot index of an element of `array`, negative ones count from the end.
+fn array_index(array: &VariantArray, index: i64) -> Option<usize> {
+    let len = array.len() as i64;
+    let index = if index < 0 { index + len } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+/// `handle` if it refers to a value of `variant_type`, the null handle otherwise.
+fn if_type(handle: u64, variant_type: VariantType) -> u64 {
+    if get(handle).get_type() == variant_type {
+        handle
+    } else {
+        0
+    }
+}
+
+extern "C" fn handle_if_string(handle: u64) -> u64 {
+    if_type(handle, VariantType::String)
+}
+
+extern "C" fn handle_if_array(handle: u64) -> u64 {
+    if_type(handle, VariantType::Array)
+}
+
+extern "C" fn handle_if_dictionary(handle: u64) -> u64 {
+    if_type(handle, VariantType::Dictionary)
+}
+
+extern "C" fn handle_string_from_name(name: u64) -> u64 {
+    names::lookup_string(name).map_or(0, |name| insert(name.to_variant()))
+}
+
+extern "C" fn handle_string_from_int(value: i64) -> u64 {
+    insert(GodotString::from(value.to_string()).to_variant())
+}
+
+extern "C" fn handle_string_from_float(value: f64) -> u64 {
+    insert(GodotString::from(value.to_string()).to_variant())
+}
+
+extern "C" fn handle_string_len(string: u64) -> i64 {
+    String::from(&self::string(string)).chars().count() as i64
+}
+
+extern "C" fn handle_string_concat(a: u64, b: u64) -> u64 {
+    let concatenated = format!("{}{}", string(a), string(b));
+    insert(GodotString::from(concatenated).to_variant())
+}
+
+extern "C" fn handle_string_eq(a: u64, b: u64) -> bool {
+    string(a) == string(b)
+}
+
+extern "C" fn handle_string_eq_name(string: u64, name: u64) -> bool {
+    names::lookup_string(name).is_some_and(|name| self::string(string) == name)
+}
+
+extern "C" fn handle_string_to_int(string: u64) -> i64 {
+    String::from(&self::string(string))
+        .trim()
+        .parse()
+        .unwrap_or_default()
+}
+
+extern "C" fn handle_string_to_float(string: u64) -> f64 {
+    String::from(&self::string(string))
+        .trim()
+        .parse()
+        .unwrap_or_default()
+}
+
+extern "C" fn handle_string_print(string: u64) {
+    godot_print!("{}", self::string(string));
+}
+
+extern "C" fn handle_array_new() -> u64 {
+    insert(VariantArray::new().to_variant())
+}
+
+extern "C" fn handle_array_len(array: u64) -> i64 {
+    self::array(array).len() as i64
+}
+
+extern "C" fn handle_array_get(array: u64, index: i64) -> u64 {
+    let array = self::array(array);
+    match array_index(&array, index) {
+        Some(index) => insert(array.get(index)),
+        None => 0,
+    }
+}
+
+extern "C" fn handle_array_set(array: u64, index: i64, value: u64) {
+    let mut array = self::array(array);
+    if let Some(index) = array_index(&array, index) {
+        array.set(index, get(value));
+    }
+}
+
+extern "C" fn handle_array_push(array: u64, value: u64) {
+    self::array(array).push(get(value));
+}
+
+extern "C" fn handle_array_remove(array: u64, index: i64) {
+    let mut array = self::array(array);
+    if let Some(index) = array_index(&array, index) {
+        array.remove(index);
+    }
+}
+
+extern "C" fn handle_dictionary_new() -> u64 {
+    insert(Dictionary::new().to_variant())
+}
+
+extern "C" fn handle_dictionary_len(dictionary: u64) -> i64 {
+    self::dictionary(dictionary).len() as i64
+}
+
+extern "C" fn handle_dictionary_get(dictionary: u64, key: u64) -> u64 {
+    self::dictionary(dictionary).get(get(key)).map_or(0, insert)
+}
+
+extern "C" fn handle_dictionary_set(dictionary: u64, key: u64, value: u64) {
+    self::dictionary(dictionary).insert(get(key), get(value));
+}
+
+extern "C" fn handle_dictionary_has(dictionary: u64, key: u64) -> bool {
+    self::dictionary(dictionary).contains_key(get(key))
+}
+
+extern "C" fn handle_dictionary_erase(dictionary: u64, key: u64) {
+    self::dictionary(dictionary).remove(get(key));
+}
+
+/// the keys as an `Array`, to iterate over
+extern "C" fn handle_dictionary_keys(dictionary: u64) -> u64 {
+    insert(self::dictionary(dictionary).keys_array().to_variant())
+}
+
+extern "C" fn handle_dictionary_values(dictionary: u64) -> u64 {
+    insert(self::dictionary(dictionary).values_array().to_variant())
+}
+
+/// Registers the externs `godot.mun` declares for `String`, `Array` and `Dictionary`.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    builder
+        .insert_fn(
+            "handle_if_string",
+            handle_if_string as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_if_array",
+            handle_if_array as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_if_dictionary",
+            handle_if_dictionary as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_string_from_name",
+            handle_string_from_name as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_string_from_int",
+            handle_string_from_int as extern "C" fn(i64) -> u64,
+        )
+        .insert_fn(
+            "handle_string_from_float",
+            handle_string_from_float as extern "C" fn(f64) -> u64,
+        )
+        .insert_fn(
+            "handle_string_len",
+            handle_string_len as extern "C" fn(u64) -> i64,
+        )
+        .insert_fn(
+            "handle_string_concat",
+            handle_string_concat as extern "C" fn(u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_string_eq",
+            handle_string_eq as extern "C" fn(u64, u64) -> bool,
+        )
+        .insert_fn(
+            "handle_string_eq_name",
+            handle_string_eq_name as extern "C" fn(u64, u64) -> bool,
+        )
+        .insert_fn(
+            "handle_string_to_int",
+            handle_string_to_int as extern "C" fn(u64) -> i64,
+        )
+        .insert_fn(
+            "handle_string_to_float",
+            handle_string_to_float as extern "C" fn(u64) -> f64,
+        )
+        .insert_fn(
+            "handle_string_print",
+            handle_string_print as extern "C" fn(u64),
+        )
+        .insert_fn(
+            "handle_array_new",
+            handle_array_new as extern "C" fn() -> u64,
+        )
+        .insert_fn(
+            "handle_array_len",
+            handle_array_len as extern "C" fn(u64) -> i64,
+        )
+        .insert_fn(
+            "handle_array_get",
+            handle_array_get as extern "C" fn(u64, i64) -> u64,
+        )
+        .insert_fn(
+            "handle_array_set",
+            handle_array_set as extern "C" fn(u64, i64, u64),
+        )
+        .insert_fn(
+            "handle_array_push",
+            handle_array_push as extern "C" fn(u64, u64),
+        )
+        .insert_fn(
+            "handle_array_remove",
+            handle_array_remove as extern "C" fn(u64, i64),
+        )
+        .insert_fn(
+            "handle_dictionary_new",
+            handle_dictionary_new as extern "C" fn() -> u64,
+        )
+        .insert_fn(
+            "handle_dictionary_len",
+            handle_dictionary_len as extern "C" fn(u64) -> i64,
+        )
+        .insert_fn(
+            "handle_dictionary_get",
+            handle_dictionary_get as extern "C" fn(u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_dictionary_set",
+            handle_dictionary_set as extern "C" fn(u64, u64, u64),
+        )
+        .insert_fn(
+            "handle_dictionary_has",
+            handle_dictionary_has as extern "C" fn(u64, u64) -> bool,
+        )
+        .insert_fn(
+            "handle_dictionary_erase",
+            handle_dictionary_erase as extern "C" fn(u64, u64),
+        )
+        .insert_fn(
+            "handle_dictionary_keys",
+            handle_dictionary_keys as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_dictionary_values",
+            handle_dictionary_values as extern "C" fn(u64) -> u64,
+        )
+}
@@ -0,0 +1,211 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use godot::{prelude::*, sys::GDExtensionCallError};
+use libffi::{middle::Cif, raw::ffi_call};
+use mun_memory::gc::GcPtr;
+use mun_runtime::Runtime;
+
+use crate::{
+    lifecycle::Callback,
+    marshal::{self, MunType, Roots, Slot},
+    state,
+};
+
+/// Calls with fewer arguments than this, not counting the state, don't allocate.
+const INLINE_ARGS: usize = 8;
+
+/// A mun function, ready to be called with arguments only known at runtime.
+pub struct PreparedFunction {
+    fn_ptr: *const std::ffi::c_void,
+    /// the arguments godot passes, not including the state
+    arg_types: Vec<MunType>,
+    return_type: MunType,
+    /// whether the first parameter is the instance's state, which is passed implicitly
+    takes_state: bool,
+    cif: Cif,
+}
+
+impl PreparedFunction {
+    /// Looks up `name` in the dispatch table of `runtime`.
+    ///
+    /// Returns `None` if there is no such function, or if its signature contains types
+    /// that can't be passed from godot.
+    pub fn new(runtime: &Runtime, name: &str) -> Option<Self> {
+        let definition = runtime.get_function_definition(name)?;
+        let signature = &definition.prototype.signature;
+        let mut arg_types = signature
+            .arg_types
+            .iter()
+            .map(MunType::from_type)
+            .collect::<Option<Vec<_>>>()?;
+        let return_type = MunType::from_type(&signature.return_type)?;
+        let cif = Cif::new(
+            arg_types.iter().map(|ty| ty.ffi_type()),
+            return_type.ffi_type(),
+        );
+
+        let takes_state = arg_types.first().is_some_and(state::is_state_type);
+        if takes_state {
+            arg_types.remove(0);
+        }
+
+        Some(Self {
+            fn_ptr: definition.fn_ptr,
+            arg_types,
+            return_type,
+            takes_state,
+            cif,
+        })
+    }
+
+    /// number of arguments godot has to pass
+    pub fn arity(&self) -> usize {
+        self.arg_types.len()
+    }
+
+    /// the types of the arguments godot passes
+    pub fn arg_types(&self) -> &[MunType] {
+        &self.arg_types
+    }
+
+    pub fn return_type(&self) -> &MunType {
+        &self.return_type
+    }
+
+    /// Calls the function, converting `args` to the types it expects, and returns the
+    /// raw return value.
+    ///
+    /// # Safety
+    /// `runtime` must be the runtime this was prepared from, with the same assembly loaded,
+    /// and `state` must have been allocated by it
+    pub unsafe fn invoke(
+        &self,
+        runtime: &Runtime,
+        state: Option<GcPtr>,
+        args: &[Variant],
+    ) -> Result<Slot, GDExtensionCallError> {
+        if args.len() != self.arity() {
+            return Err(GDExtensionCallError {
+                error: if args.len() > self.arity() {
+                    godot::sys::GDEXTENSION_CALL_ERROR_TOO_MANY_ARGUMENTS
+                } else {
+                    godot::sys::GDEXTENSION_CALL_ERROR_TOO_FEW_ARGUMENTS
+                },
+                argument: args.len() as i32,
+                expected: self.arity() as i32,
+            });
+        }
+
+        if self.arity() < INLINE_ARGS {
+            let mut slots: [Slot; INLINE_ARGS] = Default::default();
+            let mut arg_ptrs = [std::ptr::null_mut(); INLINE_ARGS];
+            self.invoke_with(runtime, state, args, &mut slots, &mut arg_ptrs)
+        } else {
+            let mut slots = (0..=self.arity())
+                .map(|_| Slot::default())
+                .collect::<Vec<_>>();
+            let mut arg_ptrs = vec![std::ptr::null_mut(); self.arity() + 1];
+            self.invoke_with(runtime, state, args, &mut slots, &mut arg_ptrs)
+        }
+    }
+
+    /// [`PreparedFunction::invoke`], with room for the state and arguments in `slots`
+    /// and `arg_ptrs`.
+    unsafe fn invoke_with(
+        &self,
+        runtime: &Runtime,
+        state: Option<GcPtr>,
+        args: &[Variant],
+        slots: &mut [Slot],
+        arg_ptrs: &mut [*mut std::ffi::c_void],
+    ) -> Result<Slot, GDExtensionCallError> {
+        // arguments the callee hasn't stored anywhere yet have to stay rooted
+        let mut roots = Roots::new(runtime);
+        let mut count = 0;
+        if self.takes_state {
+            let Some(state) = state else { return Err(invalid_method()) };
+            slots[0] = Slot::from_gc_ptr(state);
+            count = 1;
+        }
+        for (index, (arg, ty)) in args.iter().zip(&self.arg_types).enumerate() {
+            slots[count] = marshal::to_slot(&mut roots, arg, ty, index)?;
+            count += 1;
+        }
+        for (ptr, slot) in arg_ptrs.iter_mut().zip(&mut slots[..count]) {
+            *ptr = slot.as_mut_ptr();
+        }
+        let mut ret = Slot::new(&self.return_type);
+
+        ffi_call(
+            self.cif.as_raw_ptr(),
+            Some(std::mem::transmute::<*const std::ffi::c_void, unsafe extern "C" fn()>(
+                self.fn_ptr,
+            )),
+            ret.as_mut_ptr(),
+            arg_ptrs.as_mut_ptr(),
+        );
+
+        Ok(ret)
+    }
+
+    /// Calls the function, converting `args` to the types it expects.
+    ///
+    /// # Safety
+    /// see [`PreparedFunction::invoke`]
+    pub unsafe fn call(
+        &self,
+        runtime: &Runtime,
+        state: Option<GcPtr>,
+        args: &[Variant],
+    ) -> Result<Variant, GDExtensionCallError> {
+        let mut ret = self.invoke(runtime, state, args)?;
+        // why it doesn't fit has been logged already
+        marshal::from_slot(&mut ret, &self.return_type).ok_or_else(invalid_method)
+    }
+}
+
+pub fn invalid_method() -> GDExtensionCallError {
+    GDExtensionCallError {
+        error: godot::sys::GDEXTENSION_CALL_ERROR_INVALID_METHOD,
+        argument: 0,
+        expected: 0,
+    }
+}
+
+/// The functions of an assembly that instances call, resolved once per load.
+///
+/// Lifecycle callbacks are resolved up front and looked up by index, so the per-frame
+/// path neither hashes nor allocates. Other methods are resolved the first time they're
+/// called, including the ones that don't exist.
+#[derive(Default)]
+pub struct FunctionTable {
+    callbacks: [Option<Rc<PreparedFunction>>; Callback::ALL.len()],
+    methods: RefCell<HashMap<String, Option<Rc<PreparedFunction>>>>,
+}
+
+impl FunctionTable {
+    pub fn new(runtime: &Runtime) -> Self {
+        Self {
+            callbacks: Callback::ALL.map(|callback| {
+                PreparedFunction::new(runtime, callback.function_name()).map(Rc::new)
+            }),
+            methods: Default::default(),
+        }
+    }
+
+    pub fn callback(&self, callback: Callback) -> Option<&PreparedFunction> {
+        self.callbacks[callback as usize].as_deref()
+    }
+
+    /// Returns the function `name`, the table must belong to `runtime`.
+    pub fn method(&self, runtime: &Runtime, name: &str) -> Option<Rc<PreparedFunction>> {
+        if let Some(function) = self.methods.borrow().get(name) {
+            return function.clone();
+        }
+        let function = PreparedFunction::new(runtime, name).map(Rc::new);
+        self.methods
+            .borrow_mut()
+            .insert(name.to_owned(), function.clone());
+        function
+    }
+}
@@ -0,0 +1,169 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use godot::{
+    engine::global::{PropertyHint, PropertyUsageFlags},
+    prelude::*,
+};
+use mun_runtime::Runtime;
+
+use crate::{
+    marshal::MunType,
+    mun_script::PropertyInfo,
+    source_info::{self, FieldDecl},
+    state::InstanceState,
+};
+
+/// The fields of a script's `State` that are shown in the inspector, marked with
+/// `// @export` or a property hint in the source, and their default values.
+#[derive(Default)]
+pub struct Exports {
+    fields: Vec<FieldDecl>,
+    properties: Vec<PropertyInfo>,
+    defaults: HashMap<String, Variant>,
+}
+
+/// shared by a script and its instances, replaced when the script is recompiled
+pub type SharedExports = Rc<RefCell<Exports>>;
+
+impl Exports {
+    /// Collects the exports of `source`, compiled into the assembly loaded by `runtime`.
+    ///
+    /// The defaults are the values of the fields in a freshly created state.
+    pub fn new(source: &str, runtime: &Runtime) -> Self {
+        let fields: Vec<_> = source_info::state_fields(source)
+            .into_iter()
+            .filter(FieldDecl::is_exported)
+            .collect();
+
+        let Some(state) = InstanceState::new(runtime) else { return Self::default() };
+        let Some(info) = state.info(runtime) else {
+            state.release(runtime);
+            return Self::default();
+        };
+
+        let mut properties = Vec::new();
+        let mut defaults = HashMap::new();
+        for decl in &fields {
+            let Some(field) = info.field(&decl.name) else { continue };
+            properties.push(property_info(decl, &field.ty));
+            if let Some(value) = state.get(runtime, &decl.name) {
+                defaults.insert(decl.name.clone(), value);
+            }
+        }
+        state.release(runtime);
+
+        Self {
+            fields,
+            properties,
+            defaults,
+        }
+    }
+
+    pub fn is_exported(&self, name: &str) -> bool {
+        self.fields.iter().any(|field| field.name == name)
+    }
+
+    /// the exported properties, as shown in the inspector
+    pub fn property_list(&self) -> &[PropertyInfo] {
+        &self.properties
+    }
+
+    pub fn default_value(&self, name: &str) -> Option<&Variant> {
+        self.defaults.get(name)
+    }
+
+    /// Describes the state field `name` of type `ty`, unexported fields are only visible
+    /// to the debugger and aren't saved.
+    pub fn field_property(&self, name: &str, ty: &MunType) -> PropertyInfo {
+        match self.fields.iter().find(|field| field.name == name) {
+            Some(decl) => property_info(decl, ty),
+            None => {
+                let mut property = PropertyInfo::new(name, ty.variant_type());
+                property.usage = PropertyUsageFlags::PROPERTY_USAGE_SCRIPT_VARIABLE;
+                property
+            }
+        }
+    }
+}
+
+fn property_info(decl: &FieldDecl, ty: &MunType) -> PropertyInfo {
+    let mut property = PropertyInfo::new(&decl.name, ty.variant_type());
+    if let Some((hint, hint_string)) = property_hint(decl) {
+        property.hint = hint;
+        property.hint_string = GodotString::from(hint_string);
+    }
+    property
+}
+
+/// The hint from the first of these annotations on `decl`:
+///
+/// - `@range(min, max, step)`, any extra options such as `or_greater` are passed along
+/// - `@enum(Idle, Run, Jump)`
+/// - `@file("*.png")`
+/// - `@flags(Fire, Water, Earth)`
+fn property_hint(decl: &FieldDecl) -> Option<(PropertyHint, String)> {
+    decl.annotations.iter().find_map(|annotation| {
+        let hint = match annotation.name.as_str() {
+            "range" => PropertyHint::PROPERTY_HINT_RANGE,
+            "enum" => PropertyHint::PROPERTY_HINT_ENUM,
+            "file" => PropertyHint::PROPERTY_HINT_FILE,
+            "flags" => PropertyHint::PROPERTY_HINT_FLAGS,
+            _ => return None,
+        };
+        let args = annotation.args.as_deref().unwrap_or_default();
+        Some((hint, hint_string(args)))
+    })
+}
+
+/// Godot wants hint strings as a comma separated list without spaces or quotes.
+fn hint_string(args: &str) -> String {
+    args.split(',')
+        .map(|arg| arg.trim().trim_matches('"'))
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source_info::Annotation;
+
+    fn decl(annotations: &[(&str, Option<&str>)]) -> FieldDecl {
+        FieldDecl {
+            name: "field".to_owned(),
+            annotations: annotations
+                .iter()
+                .map(|&(name, args)| Annotation {
+                    name: name.to_owned(),
+                    args: args.map(str::to_owned),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn hint_strings() {
+        assert_eq!(hint_string("0, 100, 0.5"), "0,100,0.5");
+        assert_eq!(hint_string("\"*.png\", \"*.jpg\""), "*.png,*.jpg");
+        assert_eq!(hint_string("Idle, Run,"), "Idle,Run");
+        assert_eq!(hint_string(""), "");
+    }
+
+    #[test]
+    fn first_hint_annotation_wins() {
+        let decl = decl(&[
+            ("export", None),
+            ("enum", Some("Idle, Run")),
+            ("range", Some("0, 1")),
+        ]);
+        let (hint, hint_string) = property_hint(&decl).unwrap();
+        assert!(hint == PropertyHint::PROPERTY_HINT_ENUM);
+        assert_eq!(hint_string, "Idle,Run");
+    }
+
+    #[test]
+    fn export_has_no_hint() {
+        assert!(property_hint(&decl(&[("export", None)])).is_none());
+    }
+}
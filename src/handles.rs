@@ -0,0 +1,326 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    rc::{Rc, Weak},
+};
+
+use godot::prelude::*;
+use mun_memory::{
+    gc::{GcPtr, GcRuntime, HasIndirectionPtr},
+    Type,
+};
+use mun_runtime::{Runtime, RuntimeBuilder};
+
+use crate::{
+    marshal::{self, Handle},
+    names, objects,
+    runtimes::{ScriptRuntime, SharedRuntime},
+};
+
+thread_local! {
+    /// the godot values mun holds handles to, by id
+    static HANDLES: RefCell<HashMap<u64, Variant>> = RefCell::new(HashMap::new());
+    /// ids are never reused, so a stale handle reads as nil rather than another value
+    static NEXT_ID: Cell<u64> = Cell::new(1);
+    /// whether handles were created since the last [`sweep`]
+    static DIRTY: Cell<bool> = Cell::new(false);
+    /// the instance states handles can be stored in
+    static ROOTS: RefCell<Vec<(Weak<RefCell<ScriptRuntime>>, GcPtr)>> =
+        RefCell::new(Vec::new());
+}
+
+/// Stores `value` for mun, returns the id of its handle. Nil is the handle zero.
+pub fn insert(value: Variant) -> u64 {
+    if value.is_nil() {
+        return 0;
+    }
+    let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+    HANDLES.with(|handles| handles.borrow_mut().insert(id, value));
+    DIRTY.with(|dirty| dirty.set(true));
+    id
+}
+
+/// The value of handle `id`, nil if it was released.
+pub fn get(id: u64) -> Variant {
+    HANDLES
+        .with(|handles| handles.borrow().get(&id).cloned())
+        .unwrap_or_else(Variant::nil)
+}
+
+/// Makes the handles in the state at `ptr` survive sweeps, until [`remove_root`].
+pub fn add_root(runtime: &SharedRuntime, ptr: GcPtr) {
+    ROOTS.with(|roots| roots.borrow_mut().push((Rc::downgrade(runtime), ptr)));
+}
+
+pub fn remove_root(ptr: GcPtr) {
+    ROOTS.with(|roots| roots.borrow_mut().retain(|&(_, root)| root != ptr));
+}
+
+/// Releases every handle that can't be reached from an instance state.
+///
+/// Handles mun code only has in locals, or in gc structs only locals refer to, are gone
+/// once the call returns. While mun code is running this does nothing, it runs once a
+/// frame.
+pub fn sweep() {
+    if objects::is_running() || !DIRTY.with(|dirty| dirty.replace(false)) {
+        return;
+    }
+
+    let mut live = HashSet::new();
+    let mut visited = HashSet::new();
+    let complete = ROOTS.with(|roots| {
+        for (runtime, ptr) in roots.borrow().iter() {
+            let Some(runtime) = runtime.upgrade() else { continue };
+            // being hot reloaded, try again next frame rather than free too much
+            let Ok(runtime) = runtime.try_borrow() else { return false };
+            unsafe { mark_gc(&runtime, *ptr, &mut live, &mut visited) };
+        }
+        true
+    });
+    if !complete {
+        DIRTY.with(|dirty| dirty.set(true));
+        return;
+    }
+
+    HANDLES.with(|handles| handles.borrow_mut().retain(|id, _| live.contains(id)));
+}
+
+/// # Safety
+/// `ptr` must be a live gc struct allocated by `runtime`
+unsafe fn mark_gc(
+    runtime: &Runtime,
+    ptr: GcPtr,
+    live: &mut HashSet<u64>,
+    visited: &mut HashSet<GcPtr>,
+) {
+    if !visited.insert(ptr) {
+        return;
+    }
+    let ty = runtime.gc().ptr_type(ptr);
+    mark_fields(runtime, &ty, ptr.deref::<u8>(), live, visited);
+}
+
+/// Marks the handles in every field, including those of structs that can't be passed
+/// to godot.
+///
+/// # Safety
+/// `data` must point to the fields of a struct of type `ty`
+unsafe fn mark_fields(
+    runtime: &Runtime,
+    ty: &Type,
+    data: *const u8,
+    live: &mut HashSet<u64>,
+    visited: &mut HashSet<GcPtr>,
+) {
+    let Some(struct_type) = ty.as_struct() else { return };
+    for field in struct_type.fields().iter() {
+        let field_ty = field.ty();
+        let Some(field_struct) = field_ty.as_struct() else { continue };
+        let field_data = data.add(field.offset());
+        if field_struct.is_gc_struct() {
+            // gc fields of a state without a constructor can be null
+            if !marshal::is_null_gc(field_data) {
+                let ptr = std::ptr::read_unaligned(field_data as *const GcPtr);
+                mark_gc(runtime, ptr, live, visited);
+            }
+        } else if Handle::from_type(&field_ty).is_some() {
+            live.insert(std::ptr::read_unaligned(field_data as *const u64));
+        } else {
+            mark_fields(runtime, &field_ty, field_data, live, visited);
+        }
+    }
+}
+
+// constructing and converting handles
+
+extern "C" fn handle_from_int(value: i64) -> u64 {
+    insert(value.to_variant())
+}
+
+extern "C" fn handle_from_float(value: f64) -> u64 {
+    insert(value.to_variant())
+}
+
+extern "C" fn handle_from_bool(value: bool) -> u64 {
+    insert(value.to_variant())
+}
+
+extern "C" fn handle_from_object(object: u64) -> u64 {
+    insert(objects::id_to_variant(object))
+}
+
+extern "C" fn handle_from_name(name: u64) -> u64 {
+    names::lookup_string(name).map_or(0, |name| insert(name.to_variant()))
+}
+
+extern "C" fn handle_from_vector2(x: f32, y: f32) -> u64 {
+    insert(Vector2::new(x, y).to_variant())
+}
+
+extern "C" fn handle_to_int(handle: u64) -> i64 {
+    get(handle).try_to().unwrap_or_default()
+}
+
+extern "C" fn handle_to_float(handle: u64) -> f64 {
+    get(handle).try_to().unwrap_or_default()
+}
+
+extern "C" fn handle_to_bool(handle: u64) -> bool {
+    get(handle).booleanize()
+}
+
+extern "C" fn handle_to_object(handle: u64) -> u64 {
+    objects::variant_to_id(&get(handle)).unwrap_or(0)
+}
+
+extern "C" fn handle_to_vector2_x(handle: u64) -> f32 {
+    get(handle).try_to::<Vector2>().map_or(0.0, |v| v.x)
+}
+
+extern "C" fn handle_to_vector2_y(handle: u64) -> f32 {
+    get(handle).try_to::<Vector2>().map_or(0.0, |v| v.y)
+}
+
+// dynamic access to objects, for properties and methods bindings don't cover, like
+// those of gdscripts
+
+fn with_object(object: u64, f: impl FnOnce(Gd<Object>) -> Variant) -> u64 {
+    match objects::from_id::<Object>(object) {
+        Some(object) => insert(f(object)),
+        None => {
+            godot_error!("accessed an object that doesn't exist");
+            0
+        }
+    }
+}
+
+extern "C" fn handle_obj_get(object: u64, property: u64) -> u64 {
+    let Some(property) = names::lookup(property) else { return 0 };
+    with_object(object, |object| object.get(property))
+}
+
+extern "C" fn handle_obj_set(object: u64, property: u64, value: u64) {
+    let Some(property) = names::lookup(property) else { return };
+    with_object(object, |mut object| {
+        object.set(property, get(value));
+        Variant::nil()
+    });
+}
+
+fn obj_call(object: u64, method: u64, args: &[u64]) -> u64 {
+    let args = args.iter().map(|&arg| get(arg)).collect::<Vec<_>>();
+    call_variants(object, method, &args)
+}
+
+fn call_variants(object: u64, method: u64, args: &[Variant]) -> u64 {
+    let Some(method) = names::lookup(method) else { return 0 };
+    with_object(object, |mut object| object.call(method, args))
+}
+
+extern "C" fn handle_obj_call0(object: u64, method: u64) -> u64 {
+    obj_call(object, method, &[])
+}
+
+extern "C" fn handle_obj_call1(object: u64, method: u64, a: u64) -> u64 {
+    obj_call(object, method, &[a])
+}
+
+extern "C" fn handle_obj_call2(object: u64, method: u64, a: u64, b: u64) -> u64 {
+    obj_call(object, method, &[a, b])
+}
+
+extern "C" fn handle_obj_call3(object: u64, method: u64, a: u64, b: u64, c: u64) -> u64 {
+    obj_call(object, method, &[a, b, c])
+}
+
+extern "C" fn handle_obj_call4(object: u64, method: u64, a: u64, b: u64, c: u64, d: u64) -> u64 {
+    obj_call(object, method, &[a, b, c, d])
+}
+
+extern "C" fn handle_obj_callv(object: u64, method: u64, args: u64) -> u64 {
+    let args = get(args).try_to::<VariantArray>().unwrap_or_default();
+    let args = (0..args.len()).map(|i| args.get(i)).collect::<Vec<_>>();
+    call_variants(object, method, &args)
+}
+
+/// Registers the externs `godot.mun` declares for `Variant`.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    builder
+        .insert_fn(
+            "handle_from_int",
+            handle_from_int as extern "C" fn(i64) -> u64,
+        )
+        .insert_fn(
+            "handle_from_float",
+            handle_from_float as extern "C" fn(f64) -> u64,
+        )
+        .insert_fn(
+            "handle_from_bool",
+            handle_from_bool as extern "C" fn(bool) -> u64,
+        )
+        .insert_fn(
+            "handle_from_object",
+            handle_from_object as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_from_name",
+            handle_from_name as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_from_vector2",
+            handle_from_vector2 as extern "C" fn(f32, f32) -> u64,
+        )
+        .insert_fn("handle_to_int", handle_to_int as extern "C" fn(u64) -> i64)
+        .insert_fn(
+            "handle_to_float",
+            handle_to_float as extern "C" fn(u64) -> f64,
+        )
+        .insert_fn(
+            "handle_to_bool",
+            handle_to_bool as extern "C" fn(u64) -> bool,
+        )
+        .insert_fn(
+            "handle_to_object",
+            handle_to_object as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "handle_to_vector2_x",
+            handle_to_vector2_x as extern "C" fn(u64) -> f32,
+        )
+        .insert_fn(
+            "handle_to_vector2_y",
+            handle_to_vector2_y as extern "C" fn(u64) -> f32,
+        )
+        .insert_fn(
+            "handle_obj_get",
+            handle_obj_get as extern "C" fn(u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_set",
+            handle_obj_set as extern "C" fn(u64, u64, u64),
+        )
+        .insert_fn(
+            "handle_obj_call0",
+            handle_obj_call0 as extern "C" fn(u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_call1",
+            handle_obj_call1 as extern "C" fn(u64, u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_call2",
+            handle_obj_call2 as extern "C" fn(u64, u64, u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_call3",
+            handle_obj_call3 as extern "C" fn(u64, u64, u64, u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_call4",
+            handle_obj_call4 as extern "C" fn(u64, u64, u64, u64, u64, u64) -> u64,
+        )
+        .insert_fn(
+            "handle_obj_callv",
+            handle_obj_callv as extern "C" fn(u64, u64, u64) -> u64,
+        )
+}
@@ -0,0 +1,166 @@
+use godot::{
+    engine::{
+        Input, InputEvent, InputEventAction, InputEventJoypadButton, InputEventJoypadMotion,
+        InputEventKey, InputEventMouseButton, InputEventMouseMotion, InputEventScreenDrag,
+        InputEventScreenTouch,
+    },
+    prelude::*,
+};
+use mun_runtime::RuntimeBuilder;
+
+use crate::names;
+
+/// The `kind` of an `InputEvent` in mun, keep in sync with `godot.mun`.
+#[derive(Clone, Copy)]
+#[repr(i64)]
+enum InputKind {
+    Other = 0,
+    Key = 1,
+    MouseButton = 2,
+    MouseMotion = 3,
+    JoypadButton = 4,
+    JoypadMotion = 5,
+    Action = 6,
+    ScreenTouch = 7,
+    ScreenDrag = 8,
+}
+
+/// The fields of mun's `InputEvent`, fields that don't apply to an event are zero.
+struct FlatEvent {
+    kind: InputKind,
+    keycode: i64,
+    pressed: bool,
+    position: Vector2,
+    relative: Vector2,
+    /// mouse or joypad button, joypad axis, or touch index
+    button_index: i64,
+    /// action strength or joypad axis value
+    strength: f64,
+}
+
+impl FlatEvent {
+    fn new(event: &Gd<InputEvent>) -> Self {
+        let mut flat = Self {
+            kind: InputKind::Other,
+            keycode: 0,
+            pressed: event.is_pressed(),
+            position: Vector2::ZERO,
+            relative: Vector2::ZERO,
+            button_index: 0,
+            strength: 0.0,
+        };
+
+        let event = event.share();
+        if let Some(key) = event.share().try_cast::<InputEventKey>() {
+            flat.kind = InputKind::Key;
+            flat.keycode = key.get_keycode().ord() as i64;
+        } else if let Some(button) = event.share().try_cast::<InputEventMouseButton>() {
+            flat.kind = InputKind::MouseButton;
+            flat.position = button.get_position();
+            flat.button_index = button.get_button_index().ord() as i64;
+        } else if let Some(motion) = event.share().try_cast::<InputEventMouseMotion>() {
+            flat.kind = InputKind::MouseMotion;
+            flat.position = motion.get_position();
+            flat.relative = motion.get_relative();
+        } else if let Some(button) = event.share().try_cast::<InputEventJoypadButton>() {
+            flat.kind = InputKind::JoypadButton;
+            flat.button_index = button.get_button_index().ord() as i64;
+        } else if let Some(motion) = event.share().try_cast::<InputEventJoypadMotion>() {
+            flat.kind = InputKind::JoypadMotion;
+            flat.button_index = motion.get_axis().ord() as i64;
+            flat.strength = f64::from(motion.get_axis_value());
+        } else if let Some(action) = event.share().try_cast::<InputEventAction>() {
+            flat.kind = InputKind::Action;
+            flat.strength = f64::from(action.get_strength());
+        } else if let Some(touch) = event.share().try_cast::<InputEventScreenTouch>() {
+            flat.kind = InputKind::ScreenTouch;
+            flat.position = touch.get_position();
+            flat.button_index = touch.get_index();
+        } else if let Some(drag) = event.try_cast::<InputEventScreenDrag>() {
+            flat.kind = InputKind::ScreenDrag;
+            flat.position = drag.get_position();
+            flat.relative = drag.get_relative();
+            flat.button_index = drag.get_index();
+        }
+        flat
+    }
+
+    fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert("kind", self.kind as i64);
+        dict.insert("keycode", self.keycode);
+        dict.insert("pressed", self.pressed);
+        dict.insert("position", self.position);
+        dict.insert("relative", self.relative);
+        dict.insert("button_index", self.button_index);
+        dict.insert("strength", self.strength);
+        dict
+    }
+}
+
+/// Flattens `event` into a variant that converts to mun's `InputEvent`.
+pub fn flatten(event: &Gd<InputEvent>) -> Variant {
+    FlatEvent::new(event).to_dictionary().to_variant()
+}
+
+// action queries, actions are names interned by `names::rewrite_literals`
+
+extern "C" fn is_action_pressed(action: u64) -> bool {
+    let Some(action) = names::lookup(action) else {
+        return false;
+    };
+    Input::singleton().is_action_pressed(action, false)
+}
+
+extern "C" fn is_action_just_pressed(action: u64) -> bool {
+    let Some(action) = names::lookup(action) else {
+        return false;
+    };
+    Input::singleton().is_action_just_pressed(action, false)
+}
+
+extern "C" fn is_action_just_released(action: u64) -> bool {
+    let Some(action) = names::lookup(action) else {
+        return false;
+    };
+    Input::singleton().is_action_just_released(action, false)
+}
+
+extern "C" fn get_action_strength(action: u64) -> f64 {
+    let Some(action) = names::lookup(action) else {
+        return 0.0;
+    };
+    f64::from(Input::singleton().get_action_strength(action, false))
+}
+
+extern "C" fn get_axis(negative_action: u64, positive_action: u64) -> f64 {
+    let (Some(negative), Some(positive)) = (
+        names::lookup(negative_action),
+        names::lookup(positive_action),
+    ) else {
+        return 0.0;
+    };
+    f64::from(Input::singleton().get_axis(negative, positive))
+}
+
+/// Registers the externs `godot.mun` declares for querying `Input`.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    builder
+        .insert_fn(
+            "is_action_pressed",
+            is_action_pressed as extern "C" fn(u64) -> bool,
+        )
+        .insert_fn(
+            "is_action_just_pressed",
+            is_action_just_pressed as extern "C" fn(u64) -> bool,
+        )
+        .insert_fn(
+            "is_action_just_released",
+            is_action_just_released as extern "C" fn(u64) -> bool,
+        )
+        .insert_fn(
+            "get_action_strength",
+            get_action_strength as extern "C" fn(u64) -> f64,
+        )
+        .insert_fn("get_axis", get_axis as extern "C" fn(u64, u64) -> f64)
+}
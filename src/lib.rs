@@ -9,11 +9,32 @@ use regex::Regex;
 
 use crate::{mun_loader::MunFormatLoader, mun_saver::MunFormatSaver};
 
+mod bindings;
+mod compile_cache;
+mod compile_worker;
+mod compiler;
+mod containers;
+// public for `benches/dispatch.rs`, they aren't an api
+#[doc(hidden)]
+pub mod dispatch;
+mod exports;
+mod handles;
+mod input;
+#[doc(hidden)]
+pub mod lifecycle;
+mod marshal;
+mod methods;
 mod mun_extension;
 mod mun_loader;
 mod mun_saver;
 mod mun_script;
+mod names;
+mod objects;
+mod runtimes;
 mod script_instance;
+mod signals;
+mod source_info;
+mod state;
 
 struct GodotMun;
 
@@ -0,0 +1,99 @@
+use std::mem::ManuallyDrop;
+
+use godot::prelude::*;
+
+/// Godot callbacks that are dispatched to mun functions, found by name.
+///
+/// The mun functions don't start with an underscore: godot also calls `_ready` and
+/// friends through `call`, that way they won't run twice. Input is the exception, it's
+/// only delivered through `call`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Callback {
+    EnterTree,
+    ExitTree,
+    Ready,
+    /// `fn process(delta: f64)`
+    Process,
+    /// `fn physics_process(delta: f64)`
+    PhysicsProcess,
+    /// `fn input(event: InputEvent)`
+    Input,
+    /// `fn unhandled_input(event: InputEvent)`
+    UnhandledInput,
+}
+
+const NOTIFICATION_ENTER_TREE: i32 = 10;
+const NOTIFICATION_EXIT_TREE: i32 = 11;
+const NOTIFICATION_READY: i32 = 13;
+const NOTIFICATION_PHYSICS_PROCESS: i32 = 16;
+const NOTIFICATION_PROCESS: i32 = 17;
+
+thread_local! {
+    /// [`Callback::method_name`] of every callback, in the order of [`Callback::ALL`].
+    /// Interned once so looking a method up doesn't allocate, and never dropped since
+    /// godot may be gone by the time the thread exits.
+    static METHOD_NAMES: ManuallyDrop<[StringName; Callback::ALL.len()]> =
+        ManuallyDrop::new(Callback::ALL.map(|callback| StringName::from(callback.method_name())));
+}
+
+impl Callback {
+    /// in declaration order, so a callback's discriminant is its index in here
+    pub const ALL: [Callback; 7] = [
+        Callback::EnterTree,
+        Callback::ExitTree,
+        Callback::Ready,
+        Callback::Process,
+        Callback::PhysicsProcess,
+        Callback::Input,
+        Callback::UnhandledInput,
+    ];
+
+    pub fn from_notification(what: i32) -> Option<Self> {
+        Some(match what {
+            NOTIFICATION_ENTER_TREE => Callback::EnterTree,
+            NOTIFICATION_EXIT_TREE => Callback::ExitTree,
+            NOTIFICATION_READY => Callback::Ready,
+            NOTIFICATION_PHYSICS_PROCESS => Callback::PhysicsProcess,
+            NOTIFICATION_PROCESS => Callback::Process,
+            _ => return None,
+        })
+    }
+
+    /// The callback whose virtual method godot calls `name`.
+    pub fn from_method(name: &StringName) -> Option<Self> {
+        METHOD_NAMES.with(|names| {
+            let index = names.iter().position(|method| method == name)?;
+            Some(Callback::ALL[index])
+        })
+    }
+
+    /// Input isn't a notification, godot only calls its virtual method.
+    pub fn is_notification(self) -> bool {
+        !matches!(self, Callback::Input | Callback::UnhandledInput)
+    }
+
+    /// the virtual method godot calls, every frame for `_process`
+    pub fn method_name(self) -> &'static str {
+        match self {
+            Callback::EnterTree => "_enter_tree",
+            Callback::ExitTree => "_exit_tree",
+            Callback::Ready => "_ready",
+            Callback::Process => "_process",
+            Callback::PhysicsProcess => "_physics_process",
+            Callback::Input => "_input",
+            Callback::UnhandledInput => "_unhandled_input",
+        }
+    }
+
+    pub fn function_name(self) -> &'static str {
+        match self {
+            Callback::EnterTree => "enter_tree",
+            Callback::ExitTree => "exit_tree",
+            Callback::Ready => "ready",
+            Callback::Process => "process",
+            Callback::PhysicsProcess => "physics_process",
+            Callback::Input => "input",
+            Callback::UnhandledInput => "unhandled_input",
+        }
+    }
+}
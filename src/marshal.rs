@@ -0,0 +1,698 @@
+use std::rc::Rc;
+
+use godot::{prelude::*, sys::GDExtensionCallError};
+use libffi::middle::Type as FfiType;
+use mun_memory::{
+    gc::{GcPtr, GcRuntime, HasIndirectionPtr},
+    Type,
+};
+use mun_runtime::Runtime;
+
+use crate::{handles, objects};
+
+/// The mun types that can be passed between godot and mun.
+#[derive(Clone)]
+pub enum MunType {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+    /// `()`, can only be returned
+    Empty,
+    /// passed to and from godot as the object, handle value or math type from `godot.mun`
+    /// it stands for, other structs as a `Dictionary` with an entry per field
+    Struct(Rc<StructInfo>),
+}
+
+pub struct StructInfo {
+    pub ty: Type,
+    /// gc structs are passed by reference, value structs by value
+    pub is_gc: bool,
+    pub fields: Vec<FieldInfo>,
+    pub size: usize,
+    /// set for the structs from `godot.mun`, which are passed as the godot type
+    pub builtin: Option<Builtin>,
+    /// whether this is `Object` from `godot.mun`, which is passed as the object it's a
+    /// handle to
+    pub is_object: bool,
+    /// set for the handle types from `godot.mun`, which are passed as the value they're
+    /// a handle to
+    pub handle: Option<Handle>,
+}
+
+/// Godot values mun holds a handle to, see `handles.rs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handle {
+    Variant,
+    String,
+    Array,
+    Dictionary,
+}
+
+impl Handle {
+    /// The handle type `ty` is, `None` for any other type.
+    pub fn from_type(ty: &Type) -> Option<Self> {
+        StructInfo::from_type(ty)?.handle
+    }
+
+    fn from_struct(name: &str, is_gc: bool, fields: &[FieldInfo]) -> Option<Self> {
+        let handle = match name.rsplit("::").next()? {
+            "Variant" => Handle::Variant,
+            "String" => Handle::String,
+            "Array" => Handle::Array,
+            "Dictionary" => Handle::Dictionary,
+            _ => return None,
+        };
+        let layout_matches =
+            fields.len() == 1 && fields[0].name == "handle" && matches!(fields[0].ty, MunType::U64);
+        (!is_gc && layout_matches).then_some(handle)
+    }
+
+    pub fn variant_type(self) -> VariantType {
+        match self {
+            // any type
+            Handle::Variant => VariantType::Nil,
+            Handle::String => VariantType::String,
+            Handle::Array => VariantType::Array,
+            Handle::Dictionary => VariantType::Dictionary,
+        }
+    }
+
+    /// Whether a handle of this type can refer to `variant`, nil is a null handle.
+    fn accepts(self, variant: &Variant) -> bool {
+        let ty = variant.get_type();
+        self == Handle::Variant || ty == VariantType::Nil || ty == self.variant_type()
+    }
+}
+
+/// Godot math types with a mun counterpart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    Vector2,
+    Vector3,
+    Color,
+    Rect2,
+    Quaternion,
+    Transform2D,
+}
+
+impl Builtin {
+    fn from_struct(name: &str, is_gc: bool, fields: &[FieldInfo]) -> Option<Self> {
+        // the struct may be qualified by the module of the script
+        let builtin = match name.rsplit("::").next()? {
+            "Vector2" => Builtin::Vector2,
+            "Vector3" => Builtin::Vector3,
+            "Color" => Builtin::Color,
+            "Rect2" => Builtin::Rect2,
+            "Quaternion" => Builtin::Quaternion,
+            "Transform2D" => Builtin::Transform2D,
+            _ => return None,
+        };
+
+        let layout_matches = fields.len() == builtin.field_names().len()
+            && fields
+                .iter()
+                .zip(builtin.field_names())
+                .all(|(field, name)| {
+                    field.name == *name
+                        && match &field.ty {
+                            MunType::F32 => true,
+                            MunType::Struct(info) => info.builtin == Some(Builtin::Vector2),
+                            _ => false,
+                        }
+                });
+        (!is_gc && layout_matches).then_some(builtin)
+    }
+
+    /// fields of the mun struct, in the order they are declared in `godot.mun`
+    fn field_names(self) -> &'static [&'static str] {
+        match self {
+            Builtin::Vector2 => &["x", "y"],
+            Builtin::Vector3 => &["x", "y", "z"],
+            Builtin::Color => &["r", "g", "b", "a"],
+            Builtin::Rect2 => &["position", "size"],
+            Builtin::Quaternion => &["x", "y", "z", "w"],
+            Builtin::Transform2D => &["x", "y", "origin"],
+        }
+    }
+
+    pub fn variant_type(self) -> VariantType {
+        match self {
+            Builtin::Vector2 => VariantType::Vector2,
+            Builtin::Vector3 => VariantType::Vector3,
+            Builtin::Color => VariantType::Color,
+            Builtin::Rect2 => VariantType::Rect2,
+            Builtin::Quaternion => VariantType::Quaternion,
+            Builtin::Transform2D => VariantType::Transform2D,
+        }
+    }
+
+    /// The components of `variant`, in the order they are laid out in the mun struct.
+    pub fn to_floats(self, variant: &Variant) -> Option<Vec<f32>> {
+        Some(match self {
+            Builtin::Vector2 => {
+                let v = variant.try_to::<Vector2>().ok()?;
+                vec![v.x, v.y]
+            }
+            Builtin::Vector3 => {
+                let v = variant.try_to::<Vector3>().ok()?;
+                vec![v.x, v.y, v.z]
+            }
+            Builtin::Color => {
+                let c = variant.try_to::<Color>().ok()?;
+                vec![c.r, c.g, c.b, c.a]
+            }
+            Builtin::Rect2 => {
+                let r = variant.try_to::<Rect2>().ok()?;
+                vec![r.position.x, r.position.y, r.size.x, r.size.y]
+            }
+            Builtin::Quaternion => {
+                let q = variant.try_to::<Quaternion>().ok()?;
+                vec![q.x, q.y, q.z, q.w]
+            }
+            Builtin::Transform2D => {
+                let t = variant.try_to::<Transform2D>().ok()?;
+                vec![t.a.x, t.a.y, t.b.x, t.b.y, t.origin.x, t.origin.y]
+            }
+        })
+    }
+
+    /// Inverse of [`Builtin::to_floats`].
+    pub fn from_floats(self, f: &[f32]) -> Variant {
+        match self {
+            Builtin::Vector2 => Vector2::new(f[0], f[1]).to_variant(),
+            Builtin::Vector3 => Vector3::new(f[0], f[1], f[2]).to_variant(),
+            Builtin::Color => Color::from_rgba(f[0], f[1], f[2], f[3]).to_variant(),
+            Builtin::Rect2 => {
+                Rect2::new(Vector2::new(f[0], f[1]), Vector2::new(f[2], f[3])).to_variant()
+            }
+            Builtin::Quaternion => Quaternion::new(f[0], f[1], f[2], f[3]).to_variant(),
+            Builtin::Transform2D => Transform2D::from_cols(
+                Vector2::new(f[0], f[1]),
+                Vector2::new(f[2], f[3]),
+                Vector2::new(f[4], f[5]),
+            )
+            .to_variant(),
+        }
+    }
+}
+
+pub struct FieldInfo {
+    pub name: String,
+    pub ty: MunType,
+    pub offset: usize,
+}
+
+impl StructInfo {
+    pub fn from_type(ty: &Type) -> Option<Self> {
+        let struct_type = ty.as_struct()?;
+        let fields = struct_type
+            .fields()
+            .iter()
+            .map(|field| {
+                Some(FieldInfo {
+                    name: field.name().to_owned(),
+                    ty: MunType::from_type(&field.ty())?,
+                    offset: field.offset(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let is_gc = struct_type.is_gc_struct();
+        let is_object = !is_gc
+            && ty.name().rsplit("::").next() == Some("Object")
+            && fields.len() == 1
+            && matches!(fields[0].ty, MunType::U64);
+        Some(Self {
+            ty: ty.clone(),
+            is_gc,
+            builtin: Builtin::from_struct(ty.name(), is_gc, &fields),
+            is_object,
+            handle: Handle::from_struct(ty.name(), is_gc, &fields),
+            fields,
+            size: ty.value_layout().size(),
+        })
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+impl MunType {
+    pub fn from_type(ty: &Type) -> Option<Self> {
+        Some(match ty.name() {
+            "core::bool" => Self::Bool,
+            "core::i8" => Self::I8,
+            "core::i16" => Self::I16,
+            "core::i32" => Self::I32,
+            "core::i64" => Self::I64,
+            "core::i128" => Self::I128,
+            "core::u8" => Self::U8,
+            "core::u16" => Self::U16,
+            "core::u32" => Self::U32,
+            "core::u64" => Self::U64,
+            "core::u128" => Self::U128,
+            "core::f32" => Self::F32,
+            "core::f64" => Self::F64,
+            "core::empty" => Self::Empty,
+            _ => Self::Struct(Rc::new(StructInfo::from_type(ty)?)),
+        })
+    }
+
+    /// size of the value as passed to or returned from a function
+    pub fn size(&self) -> usize {
+        match self {
+            MunType::Bool | MunType::I8 | MunType::U8 => 1,
+            MunType::I16 | MunType::U16 => 2,
+            MunType::I32 | MunType::U32 | MunType::F32 => 4,
+            MunType::I64 | MunType::U64 | MunType::F64 => 8,
+            MunType::I128 | MunType::U128 => 16,
+            MunType::Empty => 0,
+            MunType::Struct(info) if info.is_gc => std::mem::size_of::<GcPtr>(),
+            MunType::Struct(info) => info.size,
+        }
+    }
+
+    /// How libffi passes the value.
+    pub fn ffi_type(&self) -> FfiType {
+        match self {
+            MunType::Bool | MunType::U8 => FfiType::u8(),
+            MunType::I8 => FfiType::i8(),
+            MunType::I16 => FfiType::i16(),
+            MunType::I32 => FfiType::i32(),
+            MunType::I64 => FfiType::i64(),
+            MunType::U16 => FfiType::u16(),
+            MunType::U32 => FfiType::u32(),
+            MunType::U64 => FfiType::u64(),
+            // libffi has no 128 bit integers, x86-64 passes them the same way as a pair of
+            // 64 bit integers. Their values go through godot as ints, see `int_variant!`
+            MunType::I128 | MunType::U128 => FfiType::structure([FfiType::u64(), FfiType::u64()]),
+            MunType::F32 => FfiType::f32(),
+            MunType::F64 => FfiType::f64(),
+            MunType::Empty => FfiType::void(),
+            MunType::Struct(info) if info.is_gc => FfiType::pointer(),
+            MunType::Struct(info) => {
+                FfiType::structure(info.fields.iter().map(|field| field.ty.ffi_type()))
+            }
+        }
+    }
+
+    /// the variant type values of this type are converted to
+    pub fn variant_type(&self) -> VariantType {
+        match self {
+            MunType::Bool => VariantType::Bool,
+            MunType::I8
+            | MunType::I16
+            | MunType::I32
+            | MunType::I64
+            | MunType::I128
+            | MunType::U8
+            | MunType::U16
+            | MunType::U32
+            | MunType::U64
+            | MunType::U128 => VariantType::Int,
+            MunType::F32 | MunType::F64 => VariantType::Float,
+            MunType::Empty => VariantType::Nil,
+            MunType::Struct(info) if info.is_object => VariantType::Object,
+            MunType::Struct(info) => match (info.handle, info.builtin) {
+                (Some(handle), _) => handle.variant_type(),
+                (None, Some(builtin)) => builtin.variant_type(),
+                (None, None) => VariantType::Dictionary,
+            },
+        }
+    }
+}
+
+/// Storage for a single value passed to, or returned from, a mun function.
+///
+/// Values of up to 32 bytes, everything but large value structs, are stored inline, so
+/// calls don't allocate.
+#[derive(Default)]
+pub struct Slot {
+    inline: [u128; 2],
+    heap: Vec<u128>,
+}
+
+impl Slot {
+    /// a zeroed slot that can hold a `ty`
+    pub fn new(ty: &MunType) -> Self {
+        // libffi writes at least a full register for return values
+        let size = ty.size().max(std::mem::size_of::<u128>());
+        let words = (size + 15) / 16;
+        let mut slot = Self::default();
+        if words > slot.inline.len() {
+            slot.heap = vec![0; words];
+        }
+        slot
+    }
+
+    pub fn from_gc_ptr(ptr: GcPtr) -> Self {
+        let mut slot = Self::default();
+        unsafe { write(slot.as_mut_ptr() as *mut u8, ptr) };
+        slot
+    }
+
+    /// # Safety
+    /// the slot must hold a gc struct
+    pub unsafe fn to_gc_ptr(&mut self) -> GcPtr {
+        read(self.as_mut_ptr() as *const u8)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut std::ffi::c_void {
+        if self.heap.is_empty() {
+            self.inline.as_mut_ptr() as *mut _
+        } else {
+            self.heap.as_mut_ptr() as *mut _
+        }
+    }
+}
+
+/// A number taken from a variant, godot freely converts between bool, int and float.
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn from_variant(variant: &Variant) -> Option<Self> {
+        match variant.get_type() {
+            VariantType::Bool => variant.try_to::<bool>().ok().map(|b| Number::Int(b as i64)),
+            VariantType::Int => variant.try_to::<i64>().ok().map(Number::Int),
+            VariantType::Float => variant.try_to::<f64>().ok().map(Number::Float),
+            _ => None,
+        }
+    }
+
+    /// converts to an integer type, truncating floats, `None` if it's out of range
+    fn to_int<T: TryFrom<i128>>(self) -> Option<T> {
+        let value = match self {
+            Number::Int(value) => value as i128,
+            Number::Float(value) if value.is_finite() => value.trunc() as i128,
+            Number::Float(_) => return None,
+        };
+        T::try_from(value).ok()
+    }
+
+    fn to_float(self) -> f64 {
+        match self {
+            Number::Int(value) => value as f64,
+            Number::Float(value) => value,
+        }
+    }
+
+    fn to_bool(self) -> bool {
+        match self {
+            Number::Int(value) => value != 0,
+            Number::Float(value) => value != 0.0,
+        }
+    }
+}
+
+/// The gc structs allocated while converting a value, rooted until the value is stored
+/// somewhere the gc can reach it, so a collection in between can't free them.
+pub struct Roots<'a> {
+    runtime: &'a Runtime,
+    ptrs: Vec<GcPtr>,
+}
+
+impl<'a> Roots<'a> {
+    pub fn new(runtime: &'a Runtime) -> Self {
+        Self {
+            runtime,
+            ptrs: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self, ty: &Type) -> GcPtr {
+        let ptr = self.runtime.gc().alloc(ty);
+        self.runtime.gc().root(ptr);
+        self.ptrs.push(ptr);
+        ptr
+    }
+}
+
+impl Drop for Roots<'_> {
+    fn drop(&mut self) {
+        for &ptr in &self.ptrs {
+            self.runtime.gc().unroot(ptr);
+        }
+    }
+}
+
+/// Converts `variant` to a value of type `ty`, for the argument at `index`. Gc structs
+/// it allocates are kept alive by `roots`.
+pub fn to_slot(
+    roots: &mut Roots,
+    variant: &Variant,
+    ty: &MunType,
+    index: usize,
+) -> Result<Slot, GDExtensionCallError> {
+    let mut slot = Slot::new(ty);
+    match unsafe { write_value(roots, variant, ty, slot.as_mut_ptr() as *mut u8) } {
+        Some(()) => Ok(slot),
+        None => Err(invalid_argument(index, ty)),
+    }
+}
+
+/// Writes `variant` as a `ty` to `dst`, returns `None` if it can't be converted.
+///
+/// # Safety
+/// `dst` must be valid for writes of `ty.size()` bytes, and suitably aligned
+unsafe fn write_value(roots: &mut Roots, variant: &Variant, ty: &MunType, dst: *mut u8) -> Option<()> {
+    if let MunType::Struct(info) = ty {
+        return write_struct(roots, variant, info, dst);
+    }
+
+    let number = Number::from_variant(variant)?;
+    match ty {
+        MunType::Bool => write(dst, number.to_bool() as u8),
+        MunType::I8 => write(dst, number.to_int::<i8>()?),
+        MunType::I16 => write(dst, number.to_int::<i16>()?),
+        MunType::I32 => write(dst, number.to_int::<i32>()?),
+        MunType::I64 => write(dst, number.to_int::<i64>()?),
+        MunType::I128 => write(dst, number.to_int::<i128>()?),
+        MunType::U8 => write(dst, number.to_int::<u8>()?),
+        MunType::U16 => write(dst, number.to_int::<u16>()?),
+        MunType::U32 => write(dst, number.to_int::<u32>()?),
+        MunType::U64 => write(dst, number.to_int::<u64>()?),
+        MunType::U128 => write(dst, number.to_int::<u128>()?),
+        MunType::F32 => write(dst, number.to_float() as f32),
+        MunType::F64 => write(dst, number.to_float()),
+        MunType::Empty | MunType::Struct(_) => return None,
+    }
+    Some(())
+}
+
+unsafe fn write<T>(dst: *mut u8, value: T) {
+    std::ptr::write_unaligned(dst as *mut T, value)
+}
+
+/// Fills in a struct from a dictionary with an entry for every field, or from the
+/// matching godot type for builtins, objects and handles.
+///
+/// Value structs are written in place, gc structs are allocated and `dst` gets the
+/// pointer to them.
+unsafe fn write_struct(
+    roots: &mut Roots,
+    variant: &Variant,
+    info: &StructInfo,
+    dst: *mut u8,
+) -> Option<()> {
+    if info.is_object {
+        write(dst, objects::variant_to_id(variant)?);
+        return Some(());
+    }
+    if let Some(handle) = info.handle {
+        if !handle.accepts(variant) {
+            return None;
+        }
+        write(dst, handles::insert(variant.clone()));
+        return Some(());
+    }
+    if let Some(builtin) = info.builtin {
+        if let Some(floats) = builtin.to_floats(variant) {
+            write_floats(info, dst, &mut floats.into_iter());
+            return Some(());
+        }
+    }
+
+    let dict = variant.try_to::<Dictionary>().ok()?;
+    let data = if info.is_gc {
+        let mut ptr = roots.alloc(&info.ty);
+        write(dst, ptr);
+        ptr.deref_mut::<u8>()
+    } else {
+        dst
+    };
+
+    for field in &info.fields {
+        let value = dict.get(field.name.as_str())?;
+        write_value(roots, &value, &field.ty, data.add(field.offset))?;
+    }
+    Some(())
+}
+
+/// Godot integers are 64 bit, larger values are an error rather than wrapping around or
+/// losing precision.
+macro_rules! int_variant {
+    ($value:expr) => {{
+        let value = $value;
+        match i64::try_from(value) {
+            Ok(value) => Variant::from(value),
+            Err(_) => {
+                godot_error!("{value} doesn't fit in a godot int");
+                return None;
+            }
+        }
+    }};
+}
+
+/// Converts the value in `slot` to a variant, `None` if it doesn't fit in one.
+///
+/// # Safety
+/// `slot` must hold a value of type `ty`
+pub unsafe fn from_slot(slot: &mut Slot, ty: &MunType) -> Option<Variant> {
+    read_value(ty, slot.as_mut_ptr() as *const u8)
+}
+
+/// # Safety
+/// `src` must point to a value of type `ty`
+unsafe fn read_value(ty: &MunType, src: *const u8) -> Option<Variant> {
+    Some(match ty {
+        MunType::Bool => Variant::from(read::<u8>(src) != 0),
+        MunType::I8 => int_variant!(read::<i8>(src)),
+        MunType::I16 => int_variant!(read::<i16>(src)),
+        MunType::I32 => int_variant!(read::<i32>(src)),
+        MunType::I64 => int_variant!(read::<i64>(src)),
+        MunType::I128 => int_variant!(read::<i128>(src)),
+        MunType::U8 => int_variant!(read::<u8>(src)),
+        MunType::U16 => int_variant!(read::<u16>(src)),
+        MunType::U32 => int_variant!(read::<u32>(src)),
+        MunType::U64 => int_variant!(read::<u64>(src)),
+        MunType::U128 => int_variant!(read::<u128>(src)),
+        MunType::F32 => Variant::from(read::<f32>(src) as f64),
+        MunType::F64 => Variant::from(read::<f64>(src)),
+        MunType::Empty => Variant::nil(),
+        MunType::Struct(info) => read_struct_variant(info, src)?,
+    })
+}
+
+unsafe fn read<T>(src: *const u8) -> T {
+    std::ptr::read_unaligned(src as *const T)
+}
+
+/// Whether the `GcPtr` at `src` is null.
+///
+/// # Safety
+/// `src` must point to a `GcPtr`
+pub unsafe fn is_null_gc(src: *const u8) -> bool {
+    read::<usize>(src) == 0
+}
+
+/// Writes the `f32` fields of a builtin, recursing into nested `Vector2`s.
+unsafe fn write_floats(info: &StructInfo, dst: *mut u8, floats: &mut impl Iterator<Item = f32>) {
+    for field in &info.fields {
+        match &field.ty {
+            MunType::Struct(nested) => write_floats(nested, dst.add(field.offset), floats),
+            _ => write(dst.add(field.offset), floats.next().unwrap_or_default()),
+        }
+    }
+}
+
+/// Inverse of [`write_floats`].
+unsafe fn read_floats(info: &StructInfo, src: *const u8, floats: &mut Vec<f32>) {
+    for field in &info.fields {
+        match &field.ty {
+            MunType::Struct(nested) => read_floats(nested, src.add(field.offset), floats),
+            _ => floats.push(read(src.add(field.offset))),
+        }
+    }
+}
+
+/// Reads a struct as its godot type if it's a builtin, object or handle, or as a
+/// dictionary otherwise.
+unsafe fn read_struct_variant(info: &StructInfo, src: *const u8) -> Option<Variant> {
+    if info.is_object {
+        return Some(objects::id_to_variant(read(src)));
+    }
+    if info.handle.is_some() {
+        return Some(handles::get(read(src)));
+    }
+    // gc fields of a state without a constructor can still be null
+    if info.is_gc && is_null_gc(src) {
+        return Some(Variant::nil());
+    }
+    Some(match info.builtin {
+        Some(builtin) => {
+            let mut floats = Vec::with_capacity(6);
+            read_floats(info, src, &mut floats);
+            builtin.from_floats(&floats)
+        }
+        None => read_struct(info, src)?.to_variant(),
+    })
+}
+
+/// Reads every field of a struct into a dictionary, recursing into nested structs.
+unsafe fn read_struct(info: &StructInfo, src: *const u8) -> Option<Dictionary> {
+    let data = if info.is_gc {
+        read::<GcPtr>(src).deref::<u8>()
+    } else {
+        src
+    };
+
+    let mut dict = Dictionary::new();
+    for field in &info.fields {
+        dict.insert(
+            field.name.as_str(),
+            read_value(&field.ty, data.add(field.offset))?,
+        );
+    }
+    Some(dict)
+}
+
+/// Reads the field `name` of the struct whose fields start at `data`, `None` if there is
+/// no such field or its value doesn't fit in a variant.
+///
+/// # Safety
+/// `data` must point to a struct laid out as described by `info`
+pub unsafe fn read_field(info: &StructInfo, data: *const u8, name: &str) -> Option<Variant> {
+    let field = info.field(name)?;
+    read_value(&field.ty, data.add(field.offset))
+}
+
+/// Writes `value` to the field `name` of the struct whose fields start at `data`,
+/// returns `None` if there is no such field or `value` doesn't fit in it.
+///
+/// # Safety
+/// `data` must point to a struct laid out as described by `info`, allocated by `runtime`
+pub unsafe fn write_field(
+    runtime: &Runtime,
+    info: &StructInfo,
+    data: *mut u8,
+    name: &str,
+    value: &Variant,
+) -> Option<()> {
+    let field = info.field(name)?;
+    // once it's stored in the struct the gc can reach what `value` allocated
+    let mut roots = Roots::new(runtime);
+    write_value(&mut roots, value, &field.ty, data.add(field.offset))
+}
+
+pub fn invalid_argument(index: usize, ty: &MunType) -> GDExtensionCallError {
+    GDExtensionCallError {
+        error: godot::sys::GDEXTENSION_CALL_ERROR_INVALID_ARGUMENT,
+        argument: index as i32,
+        expected: ty.variant_type() as i32,
+    }
+}
@@ -0,0 +1,64 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    dispatch::PreparedFunction,
+    mun_script::{MethodInfo, PropertyInfo},
+    runtimes::ScriptRuntime,
+    source_info::{self, FunctionDecl},
+};
+
+/// The public functions of a script. Which functions there are and their types come from
+/// the loaded assembly, so they are correct even while the source is being edited, the
+/// source only names their arguments.
+#[derive(Default)]
+pub struct Methods {
+    decls: Vec<FunctionDecl>,
+}
+
+/// shared by a script and its instances, replaced when the source changes
+pub type SharedMethods = Rc<RefCell<Methods>>;
+
+impl Methods {
+    pub fn new(source: &str) -> Self {
+        Self {
+            decls: source_info::functions(source),
+        }
+    }
+
+    /// Describes the function `name`, if it can be called from godot.
+    pub fn method_info(&self, runtime: &ScriptRuntime, name: &str) -> Option<MethodInfo> {
+        let function = runtime.functions().method(runtime, name)?;
+        let decl = self.decls.iter().find(|decl| decl.name == name);
+        Some(method_info(name, decl, &function))
+    }
+
+    /// the functions that can be called from godot
+    pub fn method_list(&self, runtime: &ScriptRuntime) -> Vec<MethodInfo> {
+        runtime
+            .function_names()
+            .iter()
+            .filter_map(|name| self.method_info(runtime, name))
+            .collect()
+    }
+}
+
+/// `decl` names the arguments, if the source still declares the function
+fn method_info(name: &str, decl: Option<&FunctionDecl>, function: &PreparedFunction) -> MethodInfo {
+    let decl_args = decl.map_or(&[][..], |decl| &decl.args);
+    // the state is passed implicitly, so it's the parameter that isn't an argument
+    let skipped = decl_args.len().saturating_sub(function.arity());
+    let mut info = MethodInfo::new(name);
+    info.args = function
+        .arg_types()
+        .iter()
+        .enumerate()
+        .map(|(index, ty)| {
+            let name = decl_args
+                .get(skipped + index)
+                .map_or_else(|| format!("arg{index}"), |(name, _)| name.clone());
+            PropertyInfo::new(&name, ty.variant_type())
+        })
+        .collect();
+    info.return_val = PropertyInfo::new("", function.return_type().variant_type());
+    info
+}
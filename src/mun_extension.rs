@@ -2,7 +2,7 @@ use std::cell::Cell;
 
 use godot::{
     engine::{
-        code_edit::CodeCompletionKind, file_access::ModeFlags, global, FileAccess, Script,
+        code_edit::CodeCompletionKind, file_access::ModeFlags, global, ClassDb, FileAccess, Script,
         ScriptLanguageExtension, ScriptLanguageExtensionVirtual,
     },
     prelude::*,
@@ -10,7 +10,13 @@ use godot::{
 };
 use regex::Regex;
 
-use crate::{get_base_type, mun_script::MunScript};
+use crate::{
+    compile_cache,
+    compile_worker::{self, CompileResult},
+    get_base_type, handles,
+    mun_script::MunScript,
+    runtimes,
+};
 
 #[derive(GodotClass)]
 #[class(base=ScriptLanguageExtension)]
@@ -25,7 +31,23 @@ impl ScriptLanguageExtensionVirtual for MunExtension {
     }
 
     /// run every frame
-    fn frame(&mut self) {}
+    fn frame(&mut self) {
+        for CompileResult {
+            script,
+            script_path,
+            result,
+        } in compile_worker::poll()
+        {
+            // the script may have been freed while it was compiling
+            let Some(mut script) = Gd::<MunScript>::try_from_instance_id(script) else { continue };
+            if let Err(err) = script.bind_mut().finish_compile(&script_path, result) {
+                godot_error!("failed to compile {script_path}:\n{err}");
+            }
+            MunScript::instantiate_pending(script);
+        }
+        runtimes::update_all();
+        handles::sweep();
+    }
 
     /// file extensions recognized as this type
     fn get_recognized_extensions(&self) -> PackedStringArray {
@@ -135,6 +157,33 @@ impl ScriptLanguageExtensionVirtual for MunExtension {
         script.upcast()
     }
 
+    /// the stub of a function connected to a signal in the node dock, `function_args`
+    /// are formatted as `name:Type`
+    fn make_function(
+        &self,
+        class_name: GodotString,
+        function_name: GodotString,
+        function_args: PackedStringArray,
+    ) -> GodotString {
+        let args = function_args
+            .to_vec()
+            .iter()
+            .map(|arg| {
+                let arg = String::from(arg);
+                let (name, ty) = arg.split_once(':').unwrap_or((&arg, ""));
+                format!("{name}: {}", mun_type_name(ty))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let stub = format!("pub fn {function_name}({args}) {{\n}}\n");
+
+        std::mem::forget(class_name);
+        std::mem::forget(function_name);
+        std::mem::forget(function_args);
+
+        GodotString::from(stub)
+    }
+
     fn create_script(&self) -> Gd<Object> {
         println!("extension create_script");
         Gd::<MunScript>::new_default().upcast()
@@ -187,6 +236,9 @@ impl ScriptLanguageExtensionVirtual for MunExtension {
 
     fn init_ext(&mut self) {
         println!("extension init_ext");
+        if let Err(err) = compile_cache::collect_garbage() {
+            godot_warn!("failed to clean up the mun cache: {err}");
+        }
     }
 
     /// get class name of file
@@ -247,6 +299,21 @@ impl ScriptLanguageExtensionVirtual for MunExtension {
     }
 }
 
+/// The mun type for arguments of the godot type named `ty`, objects of any class are
+/// passed as `Object`, and anything without a mun counterpart as `Variant`.
+fn mun_type_name(ty: &str) -> &str {
+    match ty {
+        "bool" => "bool",
+        "int" => "i64",
+        "float" => "f64",
+        "Vector2" | "Vector3" | "Color" | "Rect2" | "Quaternion" | "Transform2D" | "String"
+        | "Array" | "Dictionary" => ty,
+        "" | "Nil" | "Variant" => "Variant",
+        ty if ClassDb::singleton().class_exists(StringName::from(ty)) => "Object",
+        _ => "Variant",
+    }
+}
+
 pub struct AutoCompletion {
     result: global::Error,
     force: bool,
@@ -369,6 +436,9 @@ impl ::godot::obj::cap::ImplementsGodotVirtual for MunExtension {
             "_make_template" => {
                 ::godot::private::gdext_virtual_method_callback!(MunExtension,fn make_template(&self,template:GodotString,class_name:GodotString,base_class_name:GodotString,)->Gd<godot::engine::Script>)
             }
+            "_make_function" => {
+                ::godot::private::gdext_virtual_method_callback!(MunExtension,fn make_function(&self,class_name:GodotString,function_name:GodotString,function_args:PackedStringArray,)->GodotString)
+            }
             "_create_script" => {
                 ::godot::private::gdext_virtual_method_callback!(MunExtension,fn create_script(&self)->Gd<Object>)
             }
@@ -76,7 +76,7 @@ impl ResourceFormatLoaderVirtual for MunFormatLoader {
         cache_mode: i64,
     ) -> Variant {
         std::mem::forget(path.clone());
-        std::mem::forget(original_path);
+        std::mem::forget(original_path.clone());
         println!("loader load");
         let Some(file) = FileAccess::open(path, ModeFlags::READ) else { return Error::ERR_CANT_OPEN.to_variant() };
         let mut script = Gd::<MunScript>::new_default();
@@ -84,6 +84,10 @@ impl ResourceFormatLoaderVirtual for MunFormatLoader {
         let contents = file.get_as_text(false);
         let contents_string = String::from(&contents);
         script.bind_mut().source_code = contents_string;
+        let original_path = String::from(&original_path);
+        if let Err(err) = script.bind_mut().compile(&original_path) {
+            godot_error!("failed to compile {original_path}:\n{err}");
+        }
         let res = script.to_variant();
 
         res
@@ -27,9 +27,14 @@ impl ResourceFormatSaverVirtual for MunFormatSaver {
         std::mem::forget(path.clone());
         std::mem::forget(resource.share());
         println!("saver save: {}", String::from(&path));
-        let script = resource.cast::<MunScript>();
-        let Some(mut file) = FileAccess::open(path, ModeFlags::WRITE) else { return Error::ERR_CANT_OPEN };
+        let mut script = resource.cast::<MunScript>();
+        let Some(mut file) = FileAccess::open(path.clone(), ModeFlags::WRITE) else { return Error::ERR_CANT_OPEN };
         file.store_string(GodotString::from(&script.bind().source_code));
+        // the source is saved either way, compile errors only mean there is nothing new to run
+        let path = String::from(&path);
+        if let Err(err) = script.bind_mut().compile(&path) {
+            godot_error!("failed to compile {path}:\n{err}");
+        }
         Error::OK
     }
     fn set_uid(&mut self, path: GodotString, uid: i64) -> Error {
@@ -1,9 +1,12 @@
-use std::cell::Cell;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+};
 
 use godot::{
     engine::{
         global::{MethodFlags, PropertyHint, PropertyUsageFlags},
-        Script, ScriptExtension, ScriptExtensionVirtual, ScriptLanguage,
+        Engine, Script, ScriptExtension, ScriptExtensionVirtual, ScriptLanguage,
     },
     prelude::*,
     private::class_macros::out,
@@ -11,10 +14,16 @@ use godot::{
 };
 
 use crate::{
+    compile_worker::{self, CompileJob},
+    compiler,
+    exports::{Exports, SharedExports},
     get_base_type,
+    methods::{Methods, SharedMethods},
     mun_extension::MunExtension,
     null_object,
+    runtimes::{self, SharedRuntime},
     script_instance::{MunScriptInstance, MUN_SCRIPT_INSTANCE_INFO},
+    signals::{SharedSignals, Signals},
 };
 
 #[derive(GodotClass)]
@@ -24,6 +33,119 @@ pub struct MunScript {
     base: Base<ScriptExtension>,
 
     pub source_code: String,
+    /// the compiled assembly of `source_code`, if it has been compiled successfully
+    assembly_path: Option<PathBuf>,
+    /// loaded on demand, and shared by all instances of this script
+    runtime: RefCell<Option<SharedRuntime>>,
+    exports: SharedExports,
+    /// the assembly `exports` were read from, see [`MunScript::refresh_exports`]
+    exports_assembly: Cell<Option<u64>>,
+    signals: SharedSignals,
+    methods: SharedMethods,
+    /// objects that got this script before it had an assembly, see
+    /// [`MunScript::instantiate_pending`]
+    pending_owners: RefCell<Vec<InstanceId>>,
+}
+
+impl MunScript {
+    fn path(&self) -> String {
+        let path = self.base.share().upcast::<Script>().get_path();
+        String::from(&path)
+    }
+
+    /// Compiles the current source code, treating it as the script at `path`.
+    ///
+    /// In the editor this happens in the background and the result is picked up in
+    /// [`MunExtension::frame`], unless the source was compiled before. Elsewhere it
+    /// blocks until the assembly is ready.
+    pub fn compile(&mut self, path: &str) -> Result<(), String> {
+        // signals and methods are declared in the source, they don't wait for the assembly
+        *self.signals.borrow_mut() = Signals::new(&self.source_code);
+        *self.methods.borrow_mut() = Methods::new(&self.source_code);
+        if let Some(cached) = compiler::cached(path, &self.source_code) {
+            self.finish_compile(path, Ok(cached))
+        } else if Engine::singleton().is_editor_hint() {
+            compile_worker::submit(CompileJob {
+                script: self.base.instance_id(),
+                script_path: path.to_owned(),
+                source: self.source_code.clone(),
+            });
+            Ok(())
+        } else {
+            let result = compiler::compile(path, &self.source_code);
+            self.finish_compile(path, result)
+        }
+    }
+
+    /// Takes the result of compiling this script into use.
+    ///
+    /// The assembly is published to a fixed path, so runtimes that are already running
+    /// it hot reload it in [`MunExtension::frame`].
+    pub fn finish_compile(
+        &mut self,
+        path: &str,
+        result: Result<PathBuf, String>,
+    ) -> Result<(), String> {
+        let compiled_path = result?;
+        // read again once the shared runtime has the new assembly
+        self.exports_assembly.set(None);
+
+        let assembly_path =
+            compiler::publish(path, &compiled_path).map_err(|err| err.to_string())?;
+        println!("munscript compiled {path} to {}", assembly_path.display());
+        if self.assembly_path.as_ref() != Some(&assembly_path) {
+            *self.runtime.get_mut() = None;
+        }
+        self.assembly_path = Some(assembly_path);
+        Ok(())
+    }
+
+    /// Gives the objects that got `script` while it was still compiling their instance,
+    /// now that it has an assembly.
+    ///
+    /// Takes the script rather than `&self`, setting it on an object creates the
+    /// instance through it.
+    pub fn instantiate_pending(script: Gd<MunScript>) {
+        let owners = {
+            let script = script.bind();
+            if script.assembly_path.is_none() {
+                return;
+            }
+            script.pending_owners.take()
+        };
+        for owner in owners {
+            let Some(mut owner) = Gd::<Object>::try_from_instance_id(owner) else { continue };
+            // skip objects that have been given another script since
+            let current = owner.get_script().try_to::<Gd<Object>>().ok();
+            if current.map(|current| current.instance_id()) != Some(script.instance_id()) {
+                continue;
+            }
+            // setting the script an object already has does nothing
+            owner.set_script(Variant::nil());
+            owner.set_script(script.to_variant());
+        }
+    }
+
+    /// The runtime of the compiled assembly, loaded when it's first needed.
+    pub fn runtime(&self) -> Option<SharedRuntime> {
+        let mut runtime = self.runtime.borrow_mut();
+        if runtime.is_none() {
+            *runtime = runtimes::get_or_load(self.assembly_path.as_ref()?);
+        }
+        runtime.clone()
+    }
+
+    /// Collects the exports again if the shared runtime has loaded another assembly
+    /// since they were collected, so their defaults are those of the current code.
+    fn refresh_exports(&self) {
+        let Some(runtime) = self.runtime() else { return };
+        let runtime = runtime.borrow();
+        if self.exports_assembly.get() == Some(runtime.assembly()) {
+            return;
+        }
+        *self.exports.borrow_mut() = Exports::new(&self.source_code, &runtime);
+        self.exports_assembly.set(Some(runtime.assembly()));
+    }
 }
 
 impl ScriptExtensionVirtual for MunScript {
@@ -36,7 +158,9 @@ impl ScriptExtensionVirtual for MunScript {
     // should be false for invalid code for instance
     fn can_instantiate(&self) -> bool {
         println!("munscript can_instantiate");
-        true
+        // in the editor the assembly may still be compiling, objects get their instance
+        // once it's there
+        self.assembly_path.is_some() || Engine::singleton().is_editor_hint()
     }
 
     // the base of this script, for inheritance
@@ -92,7 +216,14 @@ impl ScriptExtensionVirtual for MunScript {
 
     fn reload(&mut self, keep_state: bool) -> godot::engine::global::Error {
         println!("munscript reload");
-        godot::engine::global::Error::OK
+        let path = self.path();
+        match self.compile(&path) {
+            Ok(()) => godot::engine::global::Error::OK,
+            Err(err) => {
+                godot_error!("failed to compile {path}:\n{err}");
+                godot::engine::global::Error::ERR_COMPILATION_FAILED
+            }
+        }
     }
 
     fn get_documentation(&self) -> Array<Dictionary> {
@@ -102,12 +233,20 @@ impl ScriptExtensionVirtual for MunScript {
 
     fn has_method(&self, method: StringName) -> bool {
         println!("munscript has_method");
-        false
+        let name = String::from(&method);
+        std::mem::forget(method);
+        let Some(runtime) = self.runtime() else { return false };
+        let runtime = runtime.borrow();
+        runtime.functions().method(&runtime, &name).is_some()
     }
 
     fn get_method_info(&self, method: StringName) -> Dictionary {
         println!("munscript get_method_info");
-        dict! {}
+        let name = String::from(&method);
+        std::mem::forget(method);
+        let Some(runtime) = self.runtime() else { return dict! {} };
+        let info = self.methods.borrow().method_info(&runtime.borrow(), &name);
+        info.map_or_else(Dictionary::new, Dictionary::from)
     }
 
     fn is_tool(&self) -> bool {
@@ -127,36 +266,61 @@ impl ScriptExtensionVirtual for MunScript {
 
     fn has_script_signal(&self, signal: StringName) -> bool {
         println!("munscript has_script_signal");
-        false
+        let name = String::from(&signal);
+        std::mem::forget(signal);
+        self.signals.borrow().get(&name).is_some()
     }
 
     fn get_script_signal_list(&self) -> Array<Dictionary> {
         println!("munscript get_script_signal_list");
-        Array::new()
+        let signals = self.signals.borrow();
+        Array::from_iter(signals.method_list().into_iter().map(Dictionary::from))
     }
 
     fn has_property_default_value(&self, property: StringName) -> bool {
         println!("munscript has_property_default_value");
-        false
+        let name = String::from(&property);
+        std::mem::forget(property);
+        self.refresh_exports();
+        self.exports.borrow().default_value(&name).is_some()
     }
 
     fn get_property_default_value(&self, property: StringName) -> Variant {
         println!("munscript get_property_default_value");
-        Variant::nil()
+        let name = String::from(&property);
+        std::mem::forget(property);
+        self.refresh_exports();
+        self.exports
+            .borrow()
+            .default_value(&name)
+            .cloned()
+            .unwrap_or_default()
     }
 
     fn update_exports(&mut self) {
         println!("munscript update_exports");
+        self.exports_assembly.set(None);
+        self.refresh_exports();
     }
 
     fn get_script_method_list(&self) -> Array<Dictionary> {
         println!("munscript get_script_method_list");
-        Array::new()
+        let Some(runtime) = self.runtime() else { return Array::new() };
+        let methods = self.methods.borrow().method_list(&runtime.borrow());
+        Array::from_iter(methods.into_iter().map(Dictionary::from))
     }
 
     fn get_script_property_list(&self) -> Array<Dictionary> {
         println!("munscript get_script_property_list");
-        Array::new()
+        self.refresh_exports();
+        let exports = self.exports.borrow();
+        Array::from_iter(
+            exports
+                .property_list()
+                .iter()
+                .cloned()
+                .map(Dictionary::from),
+        )
     }
 
     // returns line number of a member of the script, -1 for not found
@@ -189,10 +353,25 @@ impl ScriptExtensionVirtual for MunScript {
     }
 
     fn instance_create(&self, for_object: Gd<Object>) -> *mut std::ffi::c_void {
+        let owner = for_object.instance_id();
         std::mem::forget(for_object);
-        let path = self.base.share().upcast::<Script>().get_path();
-        let path = String::from(&path);
-        let Some(instance) = MunScriptInstance::new(path) else { return std::ptr::null_mut() };
+        let Some(runtime) = self.runtime() else {
+            let mut pending_owners = self.pending_owners.borrow_mut();
+            if !pending_owners.contains(&owner) {
+                pending_owners.push(owner);
+            }
+            return std::ptr::null_mut();
+        };
+        self.refresh_exports();
+        let script = Gd::<MunScript>::from_instance_id(self.base.instance_id());
+        let instance = MunScriptInstance::new(
+            owner,
+            script,
+            runtime,
+            self.exports.clone(),
+            self.signals.clone(),
+            self.methods.clone(),
+        );
         let instance = Box::leak(Box::new(instance));
         unsafe {
             interface_fn!(script_instance_create)(
@@ -204,12 +383,25 @@ impl ScriptExtensionVirtual for MunScript {
 }
 #[derive(Clone)]
 pub struct PropertyInfo {
-    type_: VariantType,
-    name: GodotString,
-    class_name: Option<StringName>,
-    hint: PropertyHint,
-    hint_string: GodotString,
-    usage: PropertyUsageFlags,
+    pub type_: VariantType,
+    pub name: GodotString,
+    pub class_name: Option<StringName>,
+    pub hint: PropertyHint,
+    pub hint_string: GodotString,
+    pub usage: PropertyUsageFlags,
+}
+
+impl PropertyInfo {
+    pub fn new(name: &str, type_: VariantType) -> Self {
+        Self {
+            type_,
+            name: GodotString::from(name),
+            class_name: None,
+            hint: PropertyHint::PROPERTY_HINT_NONE,
+            hint_string: GodotString::new(),
+            usage: PropertyUsageFlags::PROPERTY_USAGE_DEFAULT,
+        }
+    }
 }
 
 impl From<PropertyInfo> for Dictionary {
@@ -238,11 +430,24 @@ impl From<PropertyInfo> for Dictionary {
 }
 
 pub struct MethodInfo {
-    name: GodotString,
-    args: Vec<PropertyInfo>,
-    return_val: PropertyInfo,
-    flags: MethodFlags,
-    default_arguments: Vec<Variant>,
+    pub name: GodotString,
+    pub args: Vec<PropertyInfo>,
+    pub return_val: PropertyInfo,
+    pub flags: MethodFlags,
+    pub default_arguments: Vec<Variant>,
+}
+
+impl MethodInfo {
+    /// a method without arguments that returns nothing
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: GodotString::from(name),
+            args: Vec::new(),
+            return_val: PropertyInfo::new("", VariantType::Nil),
+            flags: MethodFlags::METHOD_FLAGS_DEFAULT,
+            default_arguments: Vec::new(),
+        }
+    }
 }
 
 impl From<MethodInfo> for Dictionary {
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use godot::prelude::*;
+use once_cell::sync::Lazy;
+
+/// Every name that was interned, by id. Filled in while compiling, which can happen on
+/// the compile worker, and read by externs on the main thread.
+static NAMES: Lazy<Mutex<HashMap<u64, String>>> = Lazy::new(Default::default);
+
+/// Returns the id of `name`.
+///
+/// Ids are a hash of the name rather than a counter, because they are baked into cached
+/// assemblies and have to be the same in every session.
+pub fn intern(name: &str) -> u64 {
+    // FNV-1a, which unlike `DefaultHasher` is guaranteed not to change
+    let id = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    NAMES
+        .lock()
+        .unwrap()
+        .entry(id)
+        .or_insert_with(|| name.to_owned());
+    id
+}
+
+/// The name with id `id`, if it was interned.
+pub fn lookup(id: u64) -> Option<StringName> {
+    NAMES.lock().unwrap().get(&id).map(StringName::from)
+}
+
+/// The name with id `id` as a string, if it was interned.
+pub fn lookup_string(id: u64) -> Option<GodotString> {
+    NAMES
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|name| GodotString::from(name.as_str()))
+}
+
+/// The name with id `id` as a node path, if it was interned.
+pub fn lookup_path(id: u64) -> Option<NodePath> {
+    let names = NAMES.lock().unwrap();
+    let name = names.get(&id)?;
+    Some(NodePath::from(&GodotString::from(name.as_str())))
+}
+
+/// Replaces string literals in `source` by the ids of the names they contain.
+///
+/// Mun has no strings, so this is how scripts refer to actions, node paths and the like:
+/// `is_action_pressed("jump")` becomes `is_action_pressed(<id>u64)`. Comments are left
+/// alone, annotations have quoted arguments.
+pub fn rewrite_literals(source: &str) -> String {
+    let mut rewritten = String::with_capacity(source.len());
+    for line in source.split_inclusive('\n') {
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '/' if chars.peek().map(|&(_, next)| next) == Some('/') => {
+                    rewritten.push_str(&line[start..]);
+                    break;
+                }
+                '"' => {
+                    let mut name = String::new();
+                    let mut escaped = false;
+                    for (_, c) in chars.by_ref() {
+                        match c {
+                            '"' if !escaped => break,
+                            '\\' if !escaped => escaped = true,
+                            c => {
+                                name.push(c);
+                                escaped = false;
+                            }
+                        }
+                    }
+                    rewritten.push_str(&format!("{}u64", intern(&name)));
+                }
+                c => rewritten.push(c),
+            }
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals_become_ids() {
+        let rewritten = rewrite_literals("is_action_pressed(\"jump\")\n");
+        let id = intern("jump");
+        assert_eq!(rewritten, format!("is_action_pressed({id}u64)\n"));
+        assert_eq!(NAMES.lock().unwrap()[&id], "jump");
+    }
+
+    #[test]
+    fn escaped_quotes_are_part_of_the_name() {
+        let rewritten = rewrite_literals(r#"f("a\"b")"#);
+        assert_eq!(rewritten, format!("f({}u64)", intern("a\"b")));
+    }
+
+    #[test]
+    fn comments_are_left_alone() {
+        let source = "f(\"a\") // @file(\"*.png\")\n// \"b\"\n";
+        let rewritten = rewrite_literals(source);
+        assert_eq!(
+            rewritten,
+            format!("f({}u64) // @file(\"*.png\")\n// \"b\"\n", intern("a"))
+        );
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        // baked into cached assemblies
+        assert_eq!(intern(""), 0xcbf29ce484222325);
+        assert_eq!(intern("a"), 0xaf63dc4c8601ec8c);
+    }
+}
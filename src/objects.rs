@@ -0,0 +1,93 @@
+use std::cell::Cell;
+
+use godot::prelude::*;
+use mun_runtime::RuntimeBuilder;
+
+use crate::names;
+
+thread_local! {
+    /// the owner of the script instance whose mun code is running, if any
+    static THIS: Cell<Option<InstanceId>> = Cell::new(None);
+}
+
+/// Runs `f` with `owner` as the object mun's `this()` returns.
+pub fn with_this<R>(owner: InstanceId, f: impl FnOnce() -> R) -> R {
+    // mun code can call into godot, which can call into another instance
+    let outer = THIS.with(|this| this.replace(Some(owner)));
+    let result = f();
+    THIS.with(|this| this.set(outer));
+    result
+}
+
+/// Whether mun code of an instance is running.
+pub fn is_running() -> bool {
+    THIS.with(|this| this.get()).is_some()
+}
+
+/// The id of the handle to `object`, as stored in mun's `Object`.
+fn to_id(object: Option<Gd<impl GodotClass>>) -> u64 {
+    object.map_or(0, |object| object.instance_id().to_i64() as u64)
+}
+
+/// The object a handle refers to, `None` if it's null, freed, or not a `T`.
+pub fn from_id<T: GodotClass>(id: u64) -> Option<Gd<T>> {
+    Gd::try_from_instance_id(InstanceId::try_from_i64(id as i64)?)
+}
+
+/// Converts an object, or nil, to a handle, `None` if it's something else.
+pub fn variant_to_id(variant: &Variant) -> Option<u64> {
+    if variant.is_nil() {
+        return Some(0);
+    }
+    let object = variant.try_to::<Gd<Object>>().ok()?;
+    Some(to_id(Some(object)))
+}
+
+pub fn id_to_variant(id: u64) -> Variant {
+    from_id::<Object>(id).map_or_else(Variant::nil, |object| object.to_variant())
+}
+
+extern "C" fn object_this() -> u64 {
+    THIS.with(|this| this.get())
+        .map_or(0, |owner| owner.to_i64() as u64)
+}
+
+extern "C" fn object_get_node(node: u64, path: u64) -> u64 {
+    let (Some(node), Some(path)) = (from_id::<Node>(node), names::lookup_path(path)) else {
+        return 0;
+    };
+    to_id(node.get_node_or_null(path))
+}
+
+extern "C" fn object_get_parent(node: u64) -> u64 {
+    to_id(from_id::<Node>(node).and_then(|node| node.get_parent()))
+}
+
+extern "C" fn object_is_instance_valid(object: u64) -> bool {
+    from_id::<Object>(object).is_some()
+}
+
+extern "C" fn object_queue_free(node: u64) {
+    if let Some(mut node) = from_id::<Node>(node) {
+        node.queue_free();
+    }
+}
+
+/// Registers the externs `godot.mun` declares for `Object`.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    builder
+        .insert_fn("object_this", object_this as extern "C" fn() -> u64)
+        .insert_fn(
+            "object_get_node",
+            object_get_node as extern "C" fn(u64, u64) -> u64,
+        )
+        .insert_fn(
+            "object_get_parent",
+            object_get_parent as extern "C" fn(u64) -> u64,
+        )
+        .insert_fn(
+            "object_is_instance_valid",
+            object_is_instance_valid as extern "C" fn(u64) -> bool,
+        )
+        .insert_fn("object_queue_free", object_queue_free as extern "C" fn(u64))
+}
@@ -0,0 +1,157 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+};
+
+use godot::prelude::godot_error;
+use mun_libloader::MunLibrary;
+use mun_runtime::Runtime;
+
+use crate::{bindings, containers, dispatch::FunctionTable, handles, input, objects, signals};
+
+/// A runtime, along with the functions resolved from the assembly it has loaded.
+pub struct ScriptRuntime {
+    runtime: Runtime,
+    functions: FunctionTable,
+    /// identifies the assembly that's loaded, see [`ScriptRuntime::assembly`]
+    assembly: u64,
+    assembly_path: PathBuf,
+    /// the public functions of the loaded assembly
+    function_names: Vec<String>,
+}
+
+impl ScriptRuntime {
+    fn new(runtime: Runtime, assembly_path: &Path) -> Self {
+        let functions = FunctionTable::new(&runtime);
+        Self {
+            runtime,
+            functions,
+            assembly: next_assembly(),
+            assembly_path: assembly_path.to_owned(),
+            function_names: function_names(assembly_path),
+        }
+    }
+
+    pub fn functions(&self) -> &FunctionTable {
+        &self.functions
+    }
+
+    /// An id of the loaded assembly, unique across runtimes and hot reloads, for caching
+    /// what was read from it.
+    pub fn assembly(&self) -> u64 {
+        self.assembly
+    }
+
+    /// Every function of the loaded assembly that can be called by name, including those
+    /// with types godot can't pass.
+    pub fn function_names(&self) -> &[String] {
+        &self.function_names
+    }
+
+    /// Hot reloads the assembly if it changed on disk, returns whether it did.
+    fn update(&mut self) -> bool {
+        if !unsafe { self.runtime.update() } {
+            return false;
+        }
+        // the old function pointers point into the unloaded assembly
+        self.functions = FunctionTable::new(&self.runtime);
+        self.assembly = next_assembly();
+        self.function_names = function_names(&self.assembly_path);
+        true
+    }
+}
+
+impl Deref for ScriptRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        &self.runtime
+    }
+}
+
+/// a runtime shared by the script it was loaded for and all of its instances
+pub type SharedRuntime = Rc<RefCell<ScriptRuntime>>;
+
+thread_local! {
+    static NEXT_ASSEMBLY: Cell<u64> = Cell::new(0);
+    /// every runtime that is currently in use, by the assembly it was loaded from
+    static LIVE_RUNTIMES: RefCell<HashMap<PathBuf, Weak<RefCell<ScriptRuntime>>>> =
+        RefCell::new(HashMap::new());
+}
+
+fn next_assembly() -> u64 {
+    NEXT_ASSEMBLY.with(|next| next.replace(next.get() + 1))
+}
+
+/// The public functions of the assembly at `assembly_path`, read from its symbols.
+fn function_names(assembly_path: &Path) -> Vec<String> {
+    let library = match unsafe { MunLibrary::new(assembly_path) } {
+        Ok(library) => library,
+        Err(err) => {
+            godot_error!(
+                "failed to read the functions of {}: {err}",
+                assembly_path.display()
+            );
+            return Vec::new();
+        }
+    };
+    let info = unsafe { library.get_info() };
+    info.symbols
+        .functions()
+        .iter()
+        .map(|function| function.prototype.name().to_owned())
+        .collect()
+}
+
+fn load(assembly_path: &Path) -> Option<Runtime> {
+    println!("loading mun runtime for {}", assembly_path.display());
+    let runtime = Runtime::builder(assembly_path);
+    let runtime = objects::register_externs(input::register_externs(runtime));
+    let runtime = handles::register_externs(bindings::register_externs(runtime));
+    let runtime = signals::register_externs(containers::register_externs(runtime));
+    match unsafe { runtime.finish() } {
+        Ok(runtime) => Some(runtime),
+        Err(err) => {
+            godot_error!("failed to load {}: {err}", assembly_path.display());
+            None
+        }
+    }
+}
+
+/// Returns the runtime of the assembly at `assembly_path`, loading it if nobody is
+/// using it yet.
+pub fn get_or_load(assembly_path: &Path) -> Option<SharedRuntime> {
+    LIVE_RUNTIMES.with(|runtimes| {
+        let mut runtimes = runtimes.borrow_mut();
+        if let Some(runtime) = runtimes.get(assembly_path).and_then(Weak::upgrade) {
+            return Some(runtime);
+        }
+
+        let runtime = ScriptRuntime::new(load(assembly_path)?, assembly_path);
+        let runtime = Rc::new(RefCell::new(runtime));
+        runtimes.insert(assembly_path.to_owned(), Rc::downgrade(&runtime));
+        Some(runtime)
+    })
+}
+
+/// Hot reloads the runtimes whose assemblies changed on disk.
+///
+/// Gc allocated structs are mapped to their new layout by mun, so instance state
+/// survives the reload.
+pub fn update_all() {
+    LIVE_RUNTIMES.with(|runtimes| {
+        let mut runtimes = runtimes.borrow_mut();
+        runtimes.retain(|_, runtime| runtime.strong_count() > 0);
+        for (path, runtime) in runtimes.iter() {
+            let Some(runtime) = runtime.upgrade() else { continue };
+            // a runtime that is mid-call can pick up the change next frame
+            let Ok(mut runtime) = runtime.try_borrow_mut() else { continue };
+            if runtime.update() {
+                println!("hot reloaded {}", path.display());
+            }
+        }
+    });
+}
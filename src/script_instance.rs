@@ -1,41 +1,203 @@
-use std::{collections::HashMap, sync::RwLock};
+use std::{collections::HashMap, sync::Mutex};
 
 use godot::{
+    engine::{Engine, InputEvent},
     prelude::*,
-    sys::{GDExtensionPropertyInfo, GDExtensionScriptInstanceInfo},
+    sys::{GDExtensionMethodInfo, GDExtensionPropertyInfo, GDExtensionScriptInstanceInfo},
 };
+use once_cell::sync::OnceCell;
 pub struct MunScriptInstance {
-    properties: RwLock<HashMap<String, Variant>>,
-    property_info: Vec<GDExtensionPropertyInfo>,
-    runtime: Runtime,
+    /// the object this is the script instance of
+    owner: InstanceId,
+    /// the script this is an instance of, for godot's `get_script`
+    script: Gd<MunScript>,
+    /// shared with every other instance of the script
+    runtime: SharedRuntime,
+    /// `None` if the script doesn't declare a `State`
+    state: Option<InstanceState>,
+    exports: SharedExports,
+    methods: SharedMethods,
+    /// property lists handed to godot, by the address of their first entry
+    property_lists: Mutex<HashMap<usize, PropertyList>>,
+    /// method lists handed to godot, by the address of their first entry
+    method_lists: Mutex<HashMap<usize, MethodList>>,
 }
 
 impl MunScriptInstance {
-    pub fn new(script_path: String) -> Option<Self> {
-        let runtime = Runtime::builder(script_path);
-        let runtime = unsafe { runtime.finish() }.ok()?;
-        Some(Self {
-            properties: Default::default(),
-            property_info: Default::default(),
+    pub fn new(
+        owner: InstanceId,
+        script: Gd<MunScript>,
+        runtime: SharedRuntime,
+        exports: SharedExports,
+        signals: SharedSignals,
+        methods: SharedMethods,
+    ) -> Self {
+        let state = InstanceState::new(&runtime.borrow());
+        if let Some(state) = &state {
+            handles::add_root(&runtime, state.ptr());
+        }
+        signals::add_owner(owner, signals);
+        Self {
+            owner,
+            script,
             runtime,
-        })
+            state,
+            exports,
+            methods,
+            property_lists: Default::default(),
+            method_lists: Default::default(),
+        }
+    }
+}
+
+impl Drop for MunScriptInstance {
+    fn drop(&mut self) {
+        signals::remove_owner(self.owner);
+        if let Some(state) = &self.state {
+            handles::remove_root(state.ptr());
+            state.release(&self.runtime.borrow());
+        }
+    }
+}
+
+/// A property list handed to godot, which owns the strings its entries point to.
+struct PropertyList {
+    infos: Vec<GDExtensionPropertyInfo>,
+    _names: Vec<StringName>,
+    _class_names: Vec<StringName>,
+    _hint_strings: Vec<GodotString>,
+}
+
+impl PropertyList {
+    fn new(properties: Vec<PropertyInfo>) -> Self {
+        let names: Vec<_> = properties
+            .iter()
+            .map(|property| StringName::from(&property.name))
+            .collect();
+        let class_names: Vec<_> = properties
+            .iter()
+            .map(|property| property.class_name.clone().unwrap_or_default())
+            .collect();
+        let hint_strings: Vec<_> = properties
+            .iter()
+            .map(|property| property.hint_string.clone())
+            .collect();
+
+        let infos = properties
+            .iter()
+            .enumerate()
+            .map(|(i, property)| GDExtensionPropertyInfo {
+                type_: property.type_ as _,
+                name: names[i].string_sys(),
+                class_name: class_names[i].string_sys(),
+                hint: property.hint.ord() as u32,
+                hint_string: hint_strings[i].string_sys(),
+                usage: property.usage.ord() as u32,
+            })
+            .collect();
+
+        Self {
+            infos,
+            _names: names,
+            _class_names: class_names,
+            _hint_strings: hint_strings,
+        }
+    }
+}
+
+/// A method list handed to godot, which owns the strings and arguments its entries
+/// point to.
+struct MethodList {
+    infos: Vec<GDExtensionMethodInfo>,
+    _names: Vec<StringName>,
+    _return_values: PropertyList,
+    _arguments: Vec<PropertyList>,
+}
+
+impl MethodList {
+    fn new(methods: Vec<MethodInfo>) -> Self {
+        let names: Vec<_> = methods
+            .iter()
+            .map(|method| StringName::from(&method.name))
+            .collect();
+        let return_values = PropertyList::new(
+            methods
+                .iter()
+                .map(|method| method.return_val.clone())
+                .collect(),
+        );
+        let arguments: Vec<_> = methods
+            .iter()
+            .map(|method| PropertyList::new(method.args.clone()))
+            .collect();
+
+        let infos = methods
+            .iter()
+            .enumerate()
+            .map(|(i, method)| GDExtensionMethodInfo {
+                name: names[i].string_sys(),
+                return_value: return_values.infos[i],
+                flags: method.flags.ord() as u32,
+                id: 0,
+                argument_count: arguments[i].infos.len() as u32,
+                arguments: arguments[i].infos.as_ptr() as *mut _,
+                default_argument_count: 0,
+                default_arguments: std::ptr::null_mut(),
+            })
+            .collect();
+
+        Self {
+            infos,
+            _names: names,
+            _return_values: return_values,
+            _arguments: arguments,
+        }
     }
 }
 
 impl MunScriptInstance {
     fn set(&self, name: String, value: Variant) -> bool {
-        let mut properties = self.properties.write().unwrap();
-        if !properties.contains_key(&name) {
-            false
-        } else {
-            properties.insert(name, value);
-            true
-        }
+        let Some(state) = &self.state else { return false };
+        state.set(&self.runtime.borrow(), &name, &value)
     }
 
     fn get(&self, name: String) -> Option<Variant> {
-        let properties = self.properties.read().unwrap();
-        properties.get(&name).map(Clone::clone)
+        self.state.as_ref()?.get(&self.runtime.borrow(), &name)
+    }
+
+    /// exported properties can be reverted to their default values
+    fn property_get_revert(&self, name: String) -> Option<Variant> {
+        self.exports.borrow().default_value(&name).cloned()
+    }
+
+    /// the fields of the state, exported ones are shown in the inspector
+    fn property_list(&self) -> Vec<PropertyInfo> {
+        let runtime = self.runtime.borrow();
+        let Some(info) = self.state.as_ref().and_then(|state| state.info(&runtime)) else {
+            return Vec::new();
+        };
+        let exports = self.exports.borrow();
+        info.fields
+            .iter()
+            .map(|field| exports.field_property(&field.name, &field.ty))
+            .collect()
+    }
+
+    /// the type of the state field `name`
+    fn property_type(&self, name: String) -> Option<VariantType> {
+        let runtime = self.runtime.borrow();
+        let info = self.state.as_ref()?.info(&runtime)?;
+        Some(info.field(&name)?.ty.variant_type())
+    }
+
+    /// the functions that can be called from godot
+    fn method_list(&self) -> Vec<MethodInfo> {
+        self.methods.borrow().method_list(&self.runtime.borrow())
+    }
+
+    fn has_method(&self, name: String) -> bool {
+        let runtime = self.runtime.borrow();
+        runtime.functions().method(&runtime, &name).is_some()
     }
 
     fn call(
@@ -43,44 +205,150 @@ impl MunScriptInstance {
         method_name: String,
         args: &[Variant],
     ) -> Result<Variant, godot::sys::GDExtensionCallError> {
-        println!("attempting to call {method_name}");
-        let ret_val: Option<i64> = match args.len() {
-            0 => self.runtime.invoke(&method_name, ()).ok(),
-            1 => {
-                let arg1 = args[0].clone();
-                let Ok(arg1) = arg1.try_to::<i64>() else { return Err(
-                    godot::sys::GDExtensionCallError {
-                        error: godot::sys::GDEXTENSION_CALL_ERROR_INVALID_ARGUMENT,
-                        argument: 0,
-                        expected: VariantType::Int as i32,
-                    }
-                )};
-                self.runtime.invoke(&method_name, (arg1,)).ok()
+        let runtime = self.runtime.borrow();
+        let Some(function) = runtime.functions().method(&runtime, &method_name) else {
+            return Err(dispatch::invalid_method());
+        };
+        let state = self.state.as_ref().map(InstanceState::ptr);
+        objects::with_this(self.owner, || unsafe {
+            function.call(&runtime, state, args)
+        })
+    }
+
+    /// Godot calls the virtual methods of callbacks too, `_process` every frame. Those
+    /// that are also notifications are dispatched there, so they don't run twice.
+    fn call_callback(
+        &self,
+        callback: Callback,
+        args: &[Variant],
+    ) -> Result<Variant, godot::sys::GDExtensionCallError> {
+        if callback.is_notification() {
+            return Err(dispatch::invalid_method());
+        }
+        self.input(callback, args)
+    }
+
+    /// Calls the mun function for `callback`, if the script has one.
+    ///
+    /// This runs every frame for every instance, so it goes straight to the function
+    /// resolved when the assembly was loaded.
+    fn dispatch_callback(&self, callback: Callback, args: &[Variant]) {
+        let runtime = self.runtime.borrow();
+        let Some(function) = runtime.functions().callback(callback) else { return };
+        let state = self.state.as_ref().map(InstanceState::ptr);
+        let result = objects::with_this(self.owner, || unsafe {
+            function.call(&runtime, state, args)
+        });
+        if let Err(err) = result {
+            godot_error!(
+                "failed to call {}: error {}",
+                callback.function_name(),
+                err.error
+            );
+        }
+    }
+
+    /// Passes the event of `_input` or `_unhandled_input` on to mun, flattened.
+    fn input(
+        &self,
+        callback: Callback,
+        args: &[Variant],
+    ) -> Result<Variant, godot::sys::GDExtensionCallError> {
+        let [event] = args else {
+            return Err(godot::sys::GDExtensionCallError {
+                error: if args.is_empty() {
+                    godot::sys::GDEXTENSION_CALL_ERROR_TOO_FEW_ARGUMENTS
+                } else {
+                    godot::sys::GDEXTENSION_CALL_ERROR_TOO_MANY_ARGUMENTS
+                },
+                argument: args.len() as i32,
+                expected: 1,
+            });
+        };
+        let Ok(event) = event.try_to::<Gd<InputEvent>>() else {
+            return Err(godot::sys::GDExtensionCallError {
+                error: godot::sys::GDEXTENSION_CALL_ERROR_INVALID_ARGUMENT,
+                argument: 0,
+                expected: VariantType::Object as i32,
+            });
+        };
+        self.dispatch_callback(callback, &[input::flatten(&event)]);
+        Ok(Variant::nil())
+    }
+
+    fn has_callback(&self, callback: Callback) -> bool {
+        self.runtime
+            .borrow()
+            .functions()
+            .callback(callback)
+            .is_some()
+    }
+
+    fn notification(&self, what: i32) {
+        // scripts aren't tools, they only run in the game
+        if is_editor() {
+            return;
+        }
+        let Some(callback) = Callback::from_notification(what) else { return };
+        let Some(mut node) = Gd::<Node>::try_from_instance_id(self.owner) else { return };
+
+        match callback {
+            Callback::Ready => {
+                // godot only processes nodes whose script asks for it
+                if self.has_callback(Callback::Process) {
+                    node.set_process(true);
+                }
+                if self.has_callback(Callback::PhysicsProcess) {
+                    node.set_physics_process(true);
+                }
+                if self.has_callback(Callback::Input) {
+                    node.set_process_input(true);
+                }
+                if self.has_callback(Callback::UnhandledInput) {
+                    node.set_process_unhandled_input(true);
+                }
+                self.dispatch_callback(callback, &[]);
             }
-            _ => {
-                return Err(godot::sys::GDExtensionCallError {
-                    error: godot::sys::GDEXTENSION_CALL_ERROR_TOO_MANY_ARGUMENTS,
-                    argument: args.len() as i32,
-                    expected: 1,
-                })
+            Callback::Process => {
+                self.dispatch_callback(callback, &[node.get_process_delta_time().to_variant()])
             }
-        };
-        let Some(ret_val) = ret_val else { return Err(godot::sys::GDExtensionCallError {
-            error: godot::sys::GDEXTENSION_CALL_ERROR_INVALID_METHOD,
-            argument: 0,
-            expected: 0,
-        })};
-        Ok(Variant::from(ret_val))
+            Callback::PhysicsProcess => self.dispatch_callback(
+                callback,
+                &[node.get_physics_process_delta_time().to_variant()],
+            ),
+            Callback::EnterTree | Callback::ExitTree => self.dispatch_callback(callback, &[]),
+            // never a notification
+            Callback::Input | Callback::UnhandledInput => {}
+        }
     }
 }
 
-use mun_runtime::{Runtime, RuntimeBuilder};
+/// Whether godot runs as the editor, which can't change, so it's only asked once.
+fn is_editor() -> bool {
+    static EDITOR: OnceCell<bool> = OnceCell::new();
+    *EDITOR.get_or_init(|| Engine::singleton().is_editor_hint())
+}
+
 pub use script_ffi::MUN_SCRIPT_INSTANCE_INFO;
 
+use crate::{
+    dispatch,
+    exports::SharedExports,
+    handles, input,
+    lifecycle::Callback,
+    methods::SharedMethods,
+    mun_script::{MethodInfo, MunScript, PropertyInfo},
+    objects,
+    runtimes::SharedRuntime,
+    signals::{self, SharedSignals},
+    state::InstanceState,
+};
+
 mod script_ffi {
-    use std::mem::ManuallyDrop;
+    use std::mem::{ManuallyDrop, MaybeUninit};
 
-    use crate::mun_script::MunScript;
+    /// arguments godot calls with, up to this many, are passed on without allocating
+    const INLINE_ARGS: usize = 8;
 
     use super::*;
     use godot::sys::*;
@@ -91,15 +359,16 @@ mod script_ffi {
             get_func: Some(get),
             get_property_list_func: Some(get_property_list),
             /// called when godot is done with the property list
-            free_property_list_func: None,
-            property_can_revert_func: None,
-            property_get_revert_func: None,
+            free_property_list_func: Some(free_property_list),
+            property_can_revert_func: Some(property_can_revert),
+            property_get_revert_func: Some(property_get_revert),
             get_owner_func: None,
             get_property_state_func: None,
-            get_method_list_func: None,
-            free_method_list_func: None,
-            get_property_type_func: None,
-            has_method_func: None,
+            get_method_list_func: Some(get_method_list),
+            /// called when godot is done with the method list
+            free_method_list_func: Some(free_method_list),
+            get_property_type_func: Some(get_property_type),
+            has_method_func: Some(has_method),
             call_func: Some(call),
             notification_func: Some(notification),
             to_string_func: None,
@@ -110,7 +379,7 @@ mod script_ffi {
             set_fallback_func: None,
             get_fallback_func: None,
             get_language_func: None,
-            free_func: None,
+            free_func: Some(free),
         };
 
     /// # Safety
@@ -136,7 +405,9 @@ mod script_ffi {
     ) -> GDExtensionBool {
         let Some(instance) = mun_instance(p_instance) else { return false as GDExtensionBool };
         let name = ManuallyDrop::new(StringName::from_string_sys(p_name as *mut _));
-        let value = Variant::from_var_sys(p_value as *mut _);
+        // godot keeps owning the value
+        let value = ManuallyDrop::new(Variant::from_var_sys(p_value as *mut _));
+        let value = Variant::clone(&value);
 
         instance.set(<String as From<&StringName>>::from(&name), value) as GDExtensionBool
     }
@@ -160,28 +431,114 @@ mod script_ffi {
         }
     }
 
+    pub unsafe extern "C" fn property_can_revert(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        p_name: GDExtensionConstStringNamePtr,
+    ) -> GDExtensionBool {
+        let Some(instance) = mun_instance(p_instance) else { return false as GDExtensionBool };
+        let name = ManuallyDrop::new(StringName::from_string_sys(p_name as *mut _));
+
+        instance
+            .property_get_revert(<String as From<&StringName>>::from(&name))
+            .is_some() as GDExtensionBool
+    }
+
+    pub unsafe extern "C" fn property_get_revert(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        p_name: GDExtensionConstStringNamePtr,
+        r_ret: GDExtensionVariantPtr,
+    ) -> GDExtensionBool {
+        let Some(instance) = mun_instance(p_instance) else { return false as GDExtensionBool };
+        let name = ManuallyDrop::new(StringName::from_string_sys(p_name as *mut _));
+
+        match instance.property_get_revert(<String as From<&StringName>>::from(&name)) {
+            Some(variant) => {
+                variant.write_var_sys(r_ret);
+                true as GDExtensionBool
+            }
+            None => false as GDExtensionBool,
+        }
+    }
+
     pub unsafe extern "C" fn get_property_list(
         p_instance: GDExtensionScriptInstanceDataPtr,
         r_count: *mut u32,
     ) -> *const GDExtensionPropertyInfo {
         let Some(instance) = mun_instance(p_instance) else { return std::ptr::null_mut() };
 
-        assert!(
-            std::mem::size_of::<usize>() <= std::mem::size_of::<u32>()
-                || instance.property_info.len() < u32::MAX as usize
-        );
-
-        let mut properties: Vec<GDExtensionPropertyInfo> = Vec::with_capacity(1);
-        properties.extend(instance.property_info.iter());
-
-        *r_count = properties.len() as u32;
-        Box::leak(Box::new(properties)).as_ptr()
+        let list = PropertyList::new(instance.property_list());
+        let ptr = list.infos.as_ptr();
+        *r_count = list.infos.len() as u32;
+        instance
+            .property_lists
+            .lock()
+            .unwrap()
+            .insert(ptr as usize, list);
+        ptr
     }
 
     pub unsafe extern "C" fn free_property_list(
         p_instance: GDExtensionScriptInstanceDataPtr,
         p_list: *const GDExtensionPropertyInfo,
     ) {
+        let Some(instance) = mun_instance(p_instance) else { return };
+        instance
+            .property_lists
+            .lock()
+            .unwrap()
+            .remove(&(p_list as usize));
+    }
+
+    pub unsafe extern "C" fn get_property_type(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        p_name: GDExtensionConstStringNamePtr,
+        r_is_valid: *mut GDExtensionBool,
+    ) -> GDExtensionVariantType {
+        let property_type = mun_instance(p_instance).and_then(|instance| {
+            let name = ManuallyDrop::new(StringName::from_string_sys(p_name as *mut _));
+            instance.property_type(<String as From<&StringName>>::from(&name))
+        });
+        *r_is_valid = property_type.is_some() as GDExtensionBool;
+        property_type.unwrap_or(VariantType::Nil) as GDExtensionVariantType
+    }
+
+    pub unsafe extern "C" fn get_method_list(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        r_count: *mut u32,
+    ) -> *const GDExtensionMethodInfo {
+        let Some(instance) = mun_instance(p_instance) else { return std::ptr::null_mut() };
+
+        let list = MethodList::new(instance.method_list());
+        let ptr = list.infos.as_ptr();
+        *r_count = list.infos.len() as u32;
+        instance
+            .method_lists
+            .lock()
+            .unwrap()
+            .insert(ptr as usize, list);
+        ptr
+    }
+
+    pub unsafe extern "C" fn free_method_list(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        p_list: *const GDExtensionMethodInfo,
+    ) {
+        let Some(instance) = mun_instance(p_instance) else { return };
+        instance
+            .method_lists
+            .lock()
+            .unwrap()
+            .remove(&(p_list as usize));
+    }
+
+    pub unsafe extern "C" fn has_method(
+        p_instance: GDExtensionScriptInstanceDataPtr,
+        p_name: GDExtensionConstStringNamePtr,
+    ) -> GDExtensionBool {
+        let Some(instance) = mun_instance(p_instance) else { return false as GDExtensionBool };
+        let name = ManuallyDrop::new(StringName::from_string_sys(p_name as *mut _));
+
+        instance.has_method(<String as From<&StringName>>::from(&name)) as GDExtensionBool
     }
 
     pub unsafe extern "C" fn call(
@@ -194,24 +551,73 @@ mod script_ffi {
     ) {
         let Some(instance) = mun_instance(p_self) else { return };
         let method = ManuallyDrop::new(StringName::from_string_sys(p_method as *mut _));
-        let args: &[Variant] =
-            std::slice::from_raw_parts(p_args as *const Variant, p_argument_count as usize);
-        match instance.call(<String as From<&StringName>>::from(&method), args) {
+        let result = with_args(p_args, p_argument_count as usize, |args| {
+            // callbacks are called every frame, they're told apart without allocating
+            match Callback::from_method(&method) {
+                Some(callback) => instance.call_callback(callback, args),
+                None => instance.call(<String as From<&StringName>>::from(&method), args),
+            }
+        });
+        match result {
             Ok(variant) => variant.write_var_sys(r_return),
             Err(err) => *r_error = err,
         }
     }
 
+    /// Calls `f` with the variants `p_args` points to, which godot keeps owning.
+    ///
+    /// # Safety
+    /// `p_args` must point to `count` pointers to variants, it may be null if there are
+    /// none
+    unsafe fn with_args<R>(
+        p_args: *const GDExtensionConstVariantPtr,
+        count: usize,
+        f: impl FnOnce(&[Variant]) -> R,
+    ) -> R {
+        if count == 0 {
+            return f(&[]);
+        }
+        let pointers = std::slice::from_raw_parts(p_args, count);
+        if count <= INLINE_ARGS {
+            // bitwise copies, which are never dropped, so godot's variants aren't either
+            let mut inline: [MaybeUninit<Variant>; INLINE_ARGS] =
+                MaybeUninit::uninit().assume_init();
+            for (slot, &pointer) in inline.iter_mut().zip(pointers) {
+                std::ptr::copy_nonoverlapping(pointer as *const Variant, slot.as_mut_ptr(), 1);
+            }
+            let args = std::slice::from_raw_parts(inline.as_ptr() as *const Variant, count);
+            f(args)
+        } else {
+            let args = pointers
+                .iter()
+                .map(|&pointer| (*(pointer as *const Variant)).clone())
+                .collect::<Vec<_>>();
+            f(&args)
+        }
+    }
+
     pub unsafe extern "C" fn get_script(
         p_instance: GDExtensionScriptInstanceDataPtr,
     ) -> GDExtensionObjectPtr {
-        let script = Box::leak(Box::new(Gd::<MunScript>::new_default()));
-        script.sys_mut() as *mut _
+        let Some(instance) = mun_instance(p_instance) else { return std::ptr::null_mut() };
+        let id = instance.script.instance_id().to_i64() as u64;
+        interface_fn!(object_get_instance_from_id)(id)
+    }
+
+    /// # Safety
+    /// instance must have been created by [`MunScript::instance_create`], and must not
+    /// be used afterwards
+    pub unsafe extern "C" fn free(p_instance: GDExtensionScriptInstanceDataPtr) {
+        if !p_instance.is_null() {
+            drop(Box::from_raw(p_instance as *mut MunScriptInstance));
+        }
     }
 
     pub unsafe extern "C" fn notification(
         p_instance: GDExtensionScriptInstanceDataPtr,
         p_what: i32,
     ) {
+        let Some(instance) = mun_instance(p_instance) else { return };
+        instance.notification(p_what);
     }
 }
@@ -0,0 +1,248 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use godot::prelude::*;
+use mun_runtime::RuntimeBuilder;
+
+use crate::{
+    handles,
+    mun_script::{MethodInfo, PropertyInfo},
+    names, objects,
+    source_info::{self, SignalDecl},
+};
+
+thread_local! {
+    /// the signals of the script of every object with a mun script instance
+    static SCRIPTED: RefCell<HashMap<InstanceId, SharedSignals>> =
+        RefCell::new(HashMap::new());
+}
+
+/// The signals a script declares with `// @signal name(arg: type, ...)`.
+#[derive(Default)]
+pub struct Signals {
+    decls: Vec<SignalDecl>,
+}
+
+/// shared by a script and its instances, replaced when the source changes
+pub type SharedSignals = Rc<RefCell<Signals>>;
+
+impl Signals {
+    pub fn new(source: &str) -> Self {
+        Self {
+            decls: source_info::signals(source),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SignalDecl> {
+        self.decls.iter().find(|decl| decl.name == name)
+    }
+
+    /// the signals, as shown in the node dock
+    pub fn method_list(&self) -> Vec<MethodInfo> {
+        self.decls.iter().map(method_info).collect()
+    }
+}
+
+fn method_info(decl: &SignalDecl) -> MethodInfo {
+    let mut info = MethodInfo::new(&decl.name);
+    info.args = decl
+        .args
+        .iter()
+        .map(|(name, ty)| PropertyInfo::new(name, variant_type(ty)))
+        .collect();
+    info
+}
+
+/// The godot type of the mun type named `ty`, nil for any other structs.
+fn variant_type(ty: &str) -> VariantType {
+    match ty {
+        "bool" => VariantType::Bool,
+        "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128" => {
+            VariantType::Int
+        }
+        "f32" | "f64" => VariantType::Float,
+        "Vector2" => VariantType::Vector2,
+        "Vector3" => VariantType::Vector3,
+        "Color" => VariantType::Color,
+        "Rect2" => VariantType::Rect2,
+        "Quaternion" => VariantType::Quaternion,
+        "Transform2D" => VariantType::Transform2D,
+        "Object" => VariantType::Object,
+        "String" => VariantType::String,
+        "Array" => VariantType::Array,
+        "Dictionary" => VariantType::Dictionary,
+        _ => VariantType::Nil,
+    }
+}
+
+/// Makes signals emitted on `owner` from mun be checked against `signals`, until
+/// [`remove_owner`].
+pub fn add_owner(owner: InstanceId, signals: SharedSignals) {
+    SCRIPTED.with(|scripted| scripted.borrow_mut().insert(owner, signals));
+}
+
+pub fn remove_owner(owner: InstanceId) {
+    SCRIPTED.with(|scripted| scripted.borrow_mut().remove(&owner));
+}
+
+/// Checks `signal` against the signals declared by the mun script of `owner`, if it has
+/// one. Signals of other objects, and those of godot classes, are left to godot.
+fn check(owner: InstanceId, signal: &str, args: &[Variant]) -> Result<(), String> {
+    let Some(signals) = SCRIPTED.with(|scripted| scripted.borrow().get(&owner).cloned()) else {
+        return Ok(());
+    };
+    let signals = signals.borrow();
+    match signals.get(signal) {
+        Some(decl) => {
+            let types = args.iter().map(Variant::get_type).collect::<Vec<_>>();
+            check_args(decl, &types)
+        }
+        None => {
+            let object = Gd::<Object>::try_from_instance_id(owner);
+            if object.is_some_and(|object| object.has_signal(StringName::from(signal))) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "signal {signal} isn't declared, add `// @signal {signal}(...)`"
+                ))
+            }
+        }
+    }
+}
+
+/// Checks the types of the arguments a signal is emitted with against its declaration.
+fn check_args(decl: &SignalDecl, types: &[VariantType]) -> Result<(), String> {
+    if decl.args.len() != types.len() {
+        return Err(format!(
+            "signal {} takes {} arguments, but was emitted with {}",
+            decl.name,
+            decl.args.len(),
+            types.len()
+        ));
+    }
+    for ((name, ty), &actual) in decl.args.iter().zip(types) {
+        let expected = variant_type(ty);
+        // nil is a null object or an empty handle
+        let accepts_nil = matches!(
+            expected,
+            VariantType::Object
+                | VariantType::String
+                | VariantType::Array
+                | VariantType::Dictionary
+        );
+        let matches = expected == VariantType::Nil
+            || actual == expected
+            || (actual == VariantType::Nil && accepts_nil);
+        if !matches {
+            return Err(format!(
+                "argument {name} of signal {} is a {ty}, but was emitted with a {actual:?}",
+                decl.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn emit(object: u64, signal: u64, args: &[Variant]) {
+    let Some(signal) = names::lookup(signal) else { return };
+    let Some(mut object) = objects::from_id::<Object>(object) else {
+        godot_error!("emitted {signal} on an object that doesn't exist");
+        return;
+    };
+    if let Err(err) = check(object.instance_id(), &String::from(&signal), args) {
+        godot_error!("{err}");
+        return;
+    }
+    object.emit_signal(signal, args);
+}
+
+fn emit_handles(object: u64, signal: u64, args: &[u64]) {
+    let args = args
+        .iter()
+        .map(|&arg| handles::get(arg))
+        .collect::<Vec<_>>();
+    emit(object, signal, &args);
+}
+
+extern "C" fn signal_emit0(object: u64, signal: u64) {
+    emit_handles(object, signal, &[])
+}
+
+extern "C" fn signal_emit1(object: u64, signal: u64, a: u64) {
+    emit_handles(object, signal, &[a])
+}
+
+extern "C" fn signal_emit2(object: u64, signal: u64, a: u64, b: u64) {
+    emit_handles(object, signal, &[a, b])
+}
+
+extern "C" fn signal_emit3(object: u64, signal: u64, a: u64, b: u64, c: u64) {
+    emit_handles(object, signal, &[a, b, c])
+}
+
+extern "C" fn signal_emit4(object: u64, signal: u64, a: u64, b: u64, c: u64, d: u64) {
+    emit_handles(object, signal, &[a, b, c, d])
+}
+
+extern "C" fn signal_emitv(object: u64, signal: u64, args: u64) {
+    let args = handles::get(args)
+        .try_to::<VariantArray>()
+        .unwrap_or_default();
+    let args = (0..args.len()).map(|i| args.get(i)).collect::<Vec<_>>();
+    emit(object, signal, &args)
+}
+
+/// Registers the externs `godot.mun` declares for emitting signals.
+pub fn register_externs(builder: RuntimeBuilder) -> RuntimeBuilder {
+    builder
+        .insert_fn("signal_emit0", signal_emit0 as extern "C" fn(u64, u64))
+        .insert_fn("signal_emit1", signal_emit1 as extern "C" fn(u64, u64, u64))
+        .insert_fn(
+            "signal_emit2",
+            signal_emit2 as extern "C" fn(u64, u64, u64, u64),
+        )
+        .insert_fn(
+            "signal_emit3",
+            signal_emit3 as extern "C" fn(u64, u64, u64, u64, u64),
+        )
+        .insert_fn(
+            "signal_emit4",
+            signal_emit4 as extern "C" fn(u64, u64, u64, u64, u64, u64),
+        )
+        .insert_fn("signal_emitv", signal_emitv as extern "C" fn(u64, u64, u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit() -> SignalDecl {
+        source_info::signals("// @signal hit(damage: i64, from: Object, data: Variant)")
+            .pop()
+            .unwrap()
+    }
+
+    #[test]
+    fn matching_args() {
+        let types = [VariantType::Int, VariantType::Object, VariantType::Vector2];
+        assert!(check_args(&hit(), &types).is_ok());
+    }
+
+    #[test]
+    fn nil_for_objects() {
+        let types = [VariantType::Int, VariantType::Nil, VariantType::Nil];
+        assert!(check_args(&hit(), &types).is_ok());
+    }
+
+    #[test]
+    fn wrong_count() {
+        assert!(check_args(&hit(), &[VariantType::Int]).is_err());
+    }
+
+    #[test]
+    fn wrong_type() {
+        let types = [VariantType::Float, VariantType::Object, VariantType::Nil];
+        assert!(check_args(&hit(), &types).is_err());
+        let types = [VariantType::Nil, VariantType::Object, VariantType::Nil];
+        assert!(check_args(&hit(), &types).is_err());
+    }
+}
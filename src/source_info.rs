@@ -0,0 +1,218 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::state::STATE_TYPE;
+
+/// An annotation on the line before a declaration, such as `// @export`.
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub name: String,
+    /// everything between the parentheses, if there are any
+    pub args: Option<String>,
+}
+
+/// A field of the `State` struct, as declared in the source.
+#[derive(Clone, Debug)]
+pub struct FieldDecl {
+    pub name: String,
+    pub annotations: Vec<Annotation>,
+}
+
+impl FieldDecl {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.name == name)
+    }
+
+    /// fields are exported with `@export`, or by giving them a property hint
+    pub fn is_exported(&self) -> bool {
+        self.annotations
+            .iter()
+            .any(|annotation| EXPORT_ANNOTATIONS.contains(&annotation.name.as_str()))
+    }
+}
+
+/// A signal, declared anywhere in the source with `// @signal hit(damage: i64)`.
+#[derive(Clone, Debug)]
+pub struct SignalDecl {
+    pub name: String,
+    /// names and mun types of the arguments
+    pub args: Vec<(String, String)>,
+}
+
+/// A `pub fn`, which godot can call.
+#[derive(Clone, Debug)]
+pub struct FunctionDecl {
+    pub name: String,
+    /// names and mun types of the parameters, including the state if it takes one
+    pub args: Vec<(String, String)>,
+}
+
+const EXPORT_ANNOTATIONS: &[&str] = &["export", "range", "enum", "file", "flags"];
+
+static ANNOTATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*///?\s*@(\w+)\s*(?:\((.*)\))?\s*$").unwrap());
+static STATE_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"(?m)^\s*(?:pub\s+)?struct\s*(?:\(\s*gc\s*\))?\s*{STATE_TYPE}\b"
+    ))
+    .unwrap()
+});
+static SIGNAL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*///?\s*@signal\s+(\w+)\s*(?:\((.*)\))?\s*$").unwrap());
+static PUB_FN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^\s*pub\s+fn\s+(\w+)\s*\(([^)]*)\)").unwrap());
+static FIELD: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(?:pub\s+)?(\w+)\s*:").unwrap());
+
+fn parse_annotation(line: &str) -> Option<Annotation> {
+    let captures = ANNOTATION.captures(line)?;
+    Some(Annotation {
+        name: captures[1].to_owned(),
+        args: captures.get(2).map(|args| args.as_str().trim().to_owned()),
+    })
+}
+
+/// The fields of the `State` struct in `source`, with their annotations.
+///
+/// This only looks at the source, the compiled assembly is the authority on which fields
+/// exist and what their types are.
+pub fn state_fields(source: &str) -> Vec<FieldDecl> {
+    let Some(start) = STATE_START.find(source) else { return Vec::new() };
+    // the brace may be on the line after the name
+    let rest = &source[start.end()..];
+    let Some(open) = rest.find('{') else { return Vec::new() };
+    let body = &rest[open + 1..];
+    let body = &body[..body.find('}').unwrap_or(body.len())];
+
+    let mut fields = Vec::new();
+    let mut annotations = Vec::new();
+    for line in body.lines() {
+        if let Some(annotation) = parse_annotation(line) {
+            annotations.push(annotation);
+            continue;
+        }
+        let code = line.split("//").next().unwrap_or_default();
+        for field in code.split(',') {
+            if let Some(captures) = FIELD.captures(field) {
+                fields.push(FieldDecl {
+                    name: captures[1].to_owned(),
+                    annotations: std::mem::take(&mut annotations),
+                });
+            }
+        }
+    }
+    fields
+}
+
+/// The signals declared in `source`.
+pub fn signals(source: &str) -> Vec<SignalDecl> {
+    source
+        .lines()
+        .filter_map(|line| SIGNAL.captures(line))
+        .map(|captures| SignalDecl {
+            name: captures[1].to_owned(),
+            args: captures
+                .get(2)
+                .map_or_else(Vec::new, |args| parse_args(args.as_str())),
+        })
+        .collect()
+}
+
+/// The public functions declared in `source`, their parameters may span several lines.
+pub fn functions(source: &str) -> Vec<FunctionDecl> {
+    PUB_FN
+        .captures_iter(source)
+        .map(|captures| FunctionDecl {
+            name: captures[1].to_owned(),
+            args: parse_args(&captures[2]),
+        })
+        .collect()
+}
+
+/// Splits `a: i64, b: f32` into names and types, arguments without a type are skipped.
+fn parse_args(args: &str) -> Vec<(String, String)> {
+    args.split(',')
+        .filter_map(|arg| {
+            let (name, ty) = arg.split_once(':')?;
+            Some((name.trim().to_owned(), ty.trim().to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_fields_with_annotations() {
+        let source = "
+pub struct(gc) State
+{
+    // @export
+    pub speed: f32,
+    // @range(0, 100)
+    health: i64, // current health
+    a: i64, b: i64,
+}
+
+struct Other {
+    ignored: i64,
+}
+";
+        let fields = state_fields(source);
+        let names = fields
+            .iter()
+            .map(|field| &field.name[..])
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["speed", "health", "a", "b"]);
+        assert!(fields[0].is_exported());
+        assert_eq!(
+            fields[1].annotation("range").unwrap().args.as_deref(),
+            Some("0, 100")
+        );
+        assert!(!fields[2].is_exported());
+    }
+
+    #[test]
+    fn no_state() {
+        assert!(state_fields("struct Stateful { a: i64 }").is_empty());
+    }
+
+    #[test]
+    fn signals_with_and_without_args() {
+        let signals = signals("// @signal died\n/// @signal hit(damage: i64, from: Object)\n");
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[0].name, "died");
+        assert!(signals[0].args.is_empty());
+        assert_eq!(signals[1].name, "hit");
+        assert_eq!(
+            signals[1].args,
+            [
+                ("damage".to_owned(), "i64".to_owned()),
+                ("from".to_owned(), "Object".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn only_public_functions() {
+        let source = "
+pub fn jump(state: State,
+            height: f32) {}
+fn helper() {}
+    pub fn ready() {}
+";
+        let functions = functions(source);
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].name, "jump");
+        assert_eq!(
+            functions[0].args,
+            [
+                ("state".to_owned(), "State".to_owned()),
+                ("height".to_owned(), "f32".to_owned())
+            ]
+        );
+        assert_eq!(functions[1].name, "ready");
+    }
+}
@@ -0,0 +1,104 @@
+use mun_memory::{
+    gc::{GcPtr, GcRuntime, HasIndirectionPtr},
+    Type,
+};
+use mun_runtime::Runtime;
+
+use godot::prelude::*;
+
+use crate::{
+    dispatch::PreparedFunction,
+    marshal::{self, MunType, StructInfo},
+};
+
+/// name of the struct that holds the state of an instance
+pub const STATE_TYPE: &str = "State";
+
+/// optional constructor for the state, a zeroed state is allocated if it's missing
+pub const STATE_CONSTRUCTOR: &str = "new_state";
+
+pub fn is_state_type(ty: &MunType) -> bool {
+    matches!(ty, MunType::Struct(info) if info.is_gc && info.ty.name() == STATE_TYPE)
+}
+
+/// The `State` struct of an instance, kept alive by rooting it in the gc.
+///
+/// Functions that take a `State` as their first parameter are passed this implicitly,
+/// and its fields are exposed as the properties of the instance.
+pub struct InstanceState {
+    ptr: GcPtr,
+}
+
+impl InstanceState {
+    /// Creates the state with `new_state`, or allocates a zeroed one, see
+    /// [`alloc_zeroed`]. Returns `None` if the script doesn't declare a `State`.
+    pub fn new(runtime: &Runtime) -> Option<Self> {
+        let ptr = match PreparedFunction::new(runtime, STATE_CONSTRUCTOR) {
+            Some(constructor) if is_state_type(constructor.return_type()) => {
+                let mut ret = unsafe { constructor.invoke(runtime, None, &[]) }.ok()?;
+                unsafe { ret.to_gc_ptr() }
+            }
+            _ => {
+                let ty = runtime.get_type_info_by_name(STATE_TYPE)?;
+                alloc_zeroed(runtime, &ty, &mut Vec::new())
+            }
+        };
+        runtime.gc().root(ptr);
+        Some(Self { ptr })
+    }
+
+    pub fn ptr(&self) -> GcPtr {
+        self.ptr
+    }
+
+    /// Layout of the state, looked up every time since hot reloading can change it.
+    pub fn info(&self, runtime: &Runtime) -> Option<StructInfo> {
+        StructInfo::from_type(&runtime.gc().ptr_type(self.ptr))
+    }
+
+    pub fn get(&self, runtime: &Runtime, name: &str) -> Option<Variant> {
+        let info = self.info(runtime)?;
+        unsafe { marshal::read_field(&info, self.ptr.deref::<u8>(), name) }
+    }
+
+    /// Returns `false` if there is no field `name`, or `value` can't be stored in it.
+    pub fn set(&self, runtime: &Runtime, name: &str, value: &Variant) -> bool {
+        let Some(info) = self.info(runtime) else { return false };
+        let mut ptr = self.ptr;
+        unsafe { marshal::write_field(runtime, &info, ptr.deref_mut::<u8>(), name, value) }
+            .is_some()
+    }
+
+    /// Unroots the state, it must not be used afterwards.
+    pub fn release(&self, runtime: &Runtime) {
+        runtime.gc().unroot(self.ptr);
+    }
+}
+
+/// Allocates a zeroed gc struct of type `ty`, along with the gc structs in its fields, so
+/// they aren't null. A field of a type that's already being allocated, like the `next` of
+/// a linked list, is left null.
+fn alloc_zeroed(runtime: &Runtime, ty: &Type, allocating: &mut Vec<Type>) -> GcPtr {
+    let mut ptr = runtime.gc().alloc(ty);
+    allocating.push(ty.clone());
+    unsafe { alloc_fields(runtime, ty, ptr.deref_mut::<u8>(), allocating) };
+    allocating.pop();
+    ptr
+}
+
+/// # Safety
+/// `data` must point to the zeroed fields of a struct of type `ty`
+unsafe fn alloc_fields(runtime: &Runtime, ty: &Type, data: *mut u8, allocating: &mut Vec<Type>) {
+    let Some(struct_type) = ty.as_struct() else { return };
+    for field in struct_type.fields().iter() {
+        let field_ty = field.ty();
+        let Some(field_struct) = field_ty.as_struct() else { continue };
+        let field_data = data.add(field.offset());
+        if !field_struct.is_gc_struct() {
+            alloc_fields(runtime, &field_ty, field_data, allocating);
+        } else if !allocating.contains(&field_ty) {
+            let ptr = alloc_zeroed(runtime, &field_ty, allocating);
+            std::ptr::write_unaligned(field_data as *mut GcPtr, ptr);
+        }
+    }
+}